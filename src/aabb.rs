@@ -0,0 +1,54 @@
+use glam as math;
+
+/// Axis-aligned bounding box in world space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: math::Vec3A,
+    pub max: math::Vec3A,
+}
+
+impl Aabb {
+    pub fn new(min: math::Vec3A, max: math::Vec3A) -> Self {
+        Aabb { min, max }
+    }
+    pub fn size(&self) -> math::Vec3A {
+        self.max - self.min
+    }
+    pub fn contains_point(&self, point: math::Vec3A) -> bool {
+        point.cmpge(self.min).all() && point.cmplt(self.max).all()
+    }
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmplt(other.max).all() && other.min.cmplt(self.max).all()
+    }
+    /// Range of distances along the ray over which it is inside the box, or None if it misses.
+    /// The range may start behind the origin when the origin is inside the box.
+    pub fn ray_intersection(&self, origin: math::Vec3A, dir: math::Vec3A) -> Option<(f32, f32)> {
+        let mut enter = f32::NEG_INFINITY;
+        let mut exit = f32::INFINITY;
+        for &(o, d, min, max) in &[
+            (origin.x(), dir.x(), self.min.x(), self.max.x()),
+            (origin.y(), dir.y(), self.min.y(), self.max.y()),
+            (origin.z(), dir.z(), self.min.z(), self.max.z()),
+        ] {
+            if d == 0.0 {
+                // Parallel to the slab
+                if o < min || o >= max {
+                    return None;
+                }
+                continue;
+            }
+            let (t0, t1) = ((min - o) / d, (max - o) / d);
+            enter = enter.max(t0.min(t1));
+            exit = exit.min(t0.max(t1));
+        }
+        if enter <= exit {
+            Some((enter, exit))
+        } else {
+            None
+        }
+    }
+    pub fn intersects_sphere(&self, center: math::Vec3A, radius: f32) -> bool {
+        let closest = center.max(self.min).min(self.max);
+        (closest - center).length_squared() < radius * radius
+    }
+}
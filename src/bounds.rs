@@ -11,6 +11,18 @@ pub struct Bounds {
     width: u32,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub enum BoundsError {
+    /// The location or width doesn't fit into the fixed-point representation.
+    Overflow,
+    /// The grid has no cells.
+    ZeroGridSize,
+    /// The bounds are wider than the whole grid.
+    WidthExceedsGridSize,
+    /// The bounds extend past the far side of the grid.
+    OutsideGrid,
+}
+
 #[derive(Debug)]
 pub enum BoundsSpacialRelationship {
     Disjoint,
@@ -29,12 +41,32 @@ impl Bounds {
         }
     }
     pub fn from_discrete_grid(location: (u64, u64, u64), width: u64, gridsize: u64) -> Self {
-        Bounds {
-            x: (location.0 * Self::MAX_WIDTH as u64 / gridsize).try_into().unwrap(),
-            y: (location.1 * Self::MAX_WIDTH as u64 / gridsize).try_into().unwrap(),
-            z: (location.2 * Self::MAX_WIDTH as u64 / gridsize).try_into().unwrap(),
-            width: (width * Self::MAX_WIDTH as u64 / gridsize).try_into().unwrap(),
+        Self::try_from_discrete_grid(location, width, gridsize)
+            .expect("Discrete grid location out of range")
+    }
+    pub fn try_from_discrete_grid(location: (u64, u64, u64), width: u64, gridsize: u64) -> Result<Self, BoundsError> {
+        if gridsize == 0 {
+            return Err(BoundsError::ZeroGridSize);
+        }
+        if width > gridsize {
+            return Err(BoundsError::WidthExceedsGridSize);
+        }
+        let inside = |val: u64| val.checked_add(width).is_some_and(|end| end <= gridsize);
+        if !(inside(location.0) && inside(location.1) && inside(location.2)) {
+            return Err(BoundsError::OutsideGrid);
         }
+        // The product only fits in u128 for grids finer than the fixed-point representation
+        let convert = |val: u64| -> Result<u32, BoundsError> {
+            (val as u128 * Self::MAX_WIDTH as u128 / gridsize as u128)
+                .try_into()
+                .map_err(|_| BoundsError::Overflow)
+        };
+        Ok(Bounds {
+            x: convert(location.0)?,
+            y: convert(location.1)?,
+            z: convert(location.2)?,
+            width: convert(width)?,
+        })
     }
     pub fn get_position_with_gridsize(&self, gridsize: u64) -> (u64, u64, u64) {
         (
@@ -56,6 +88,10 @@ impl Bounds {
     pub fn get_width(&self) -> f32 {
         self.width as f32 / Self::MAX_WIDTH as f32
     }
+    /// Number of subdivisions from the chunk bounds down to this one.
+    pub fn depth(&self) -> u8 {
+        (Self::MAX_WIDTH.trailing_zeros() - self.width.trailing_zeros()) as u8
+    }
     pub fn center(&self) -> math::Vec3A {
         let half_width = self.get_width() / 2.0;
         self.get_position() + math::Vec3A::splat(half_width)
@@ -135,3 +171,30 @@ impl std::fmt::Debug for Bounds {
         write!(f, "Bounds({}, {}, {})[{}]", pos.0, pos.1, pos.2, width)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_from_discrete_grid() {
+        let bounds = Bounds::try_from_discrete_grid((1, 2, 3), 1, 4).unwrap();
+        assert_eq!(bounds.get_position_with_gridsize(4), (1, 2, 3));
+        assert_eq!(bounds.get_width_with_gridsize(4), 1);
+        assert_eq!(bounds.depth(), 2);
+
+        let bounds = Bounds::try_from_discrete_grid((1 << 40, 0, 0), 1, 1 << 41).unwrap();
+        assert_eq!(bounds.x, 1 << 30);
+        let bounds = Bounds::try_from_discrete_grid((u64::MAX - 1, 0, 0), 1, u64::MAX).unwrap();
+        assert_eq!(bounds.x, (1 << 31) - 1);
+        assert_eq!(Bounds::try_from_discrete_grid((4, 0, 0), 1, 1).unwrap_err(), BoundsError::OutsideGrid);
+        assert_eq!(Bounds::try_from_discrete_grid((0, 0, u64::MAX), 1, 256).unwrap_err(), BoundsError::OutsideGrid);
+        assert_eq!(Bounds::try_from_discrete_grid((0, 4, 0), 1, 4).unwrap_err(), BoundsError::OutsideGrid);
+        assert_eq!(Bounds::try_from_discrete_grid((0, 0, 7), 1, 4).unwrap_err(), BoundsError::OutsideGrid);
+        assert_eq!(Bounds::try_from_discrete_grid((3, 0, 0), 2, 4).unwrap_err(), BoundsError::OutsideGrid);
+        assert!(Bounds::try_from_discrete_grid((3, 3, 3), 1, 4).is_ok());
+        assert_eq!(Bounds::try_from_discrete_grid((0, 0, 0), 1, 0).unwrap_err(), BoundsError::ZeroGridSize);
+        assert_eq!(Bounds::try_from_discrete_grid((0, 0, 0), 5, 4).unwrap_err(), BoundsError::WidthExceedsGridSize);
+        assert!(Bounds::try_from_discrete_grid((0, 0, 0), 4, 4).is_ok());
+    }
+}
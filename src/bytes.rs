@@ -0,0 +1,172 @@
+use std::io::{self, Read, Write};
+use std::collections::HashMap;
+use crate::density::DensityVoxel;
+use crate::chunk::Chunk;
+use crate::mesher::MeshStyle;
+use crate::node::Node;
+use crate::direction::Direction;
+use crate::index_path::IndexPath;
+
+/// Serialization of voxel data for the binary formats.
+/// Multi-byte integers are always written little-endian.
+pub trait ToBytes {
+    fn write_bytes<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+}
+
+pub trait FromBytes: Sized {
+    fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! impl_bytes_for_primitive {
+    ($($ty:ty),*) => {
+        $(
+            impl ToBytes for $ty {
+                fn write_bytes<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+            }
+            impl FromBytes for $ty {
+                fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Self> {
+                    let mut buf = [0; std::mem::size_of::<$ty>()];
+                    reader.read_exact(&mut buf)?;
+                    Ok(<$ty>::from_le_bytes(buf))
+                }
+            }
+        )*
+    };
+}
+
+impl_bytes_for_primitive!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl ToBytes for DensityVoxel {
+    fn write_bytes<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.density.write_bytes(writer)?;
+        self.material.write_bytes(writer)
+    }
+}
+
+impl FromBytes for DensityVoxel {
+    fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(DensityVoxel {
+            density: i8::read_bytes(reader)?,
+            material: u16::read_bytes(reader)?,
+        })
+    }
+}
+
+// Nodes are written depth first: a mask of the subdivided octants,
+// the eight values, then each subdivided child in direction order.
+fn write_node<T: ToBytes, W: Write>(node: &Node<T>, writer: &mut W) -> io::Result<()> {
+    let mask = node.children_present().fold(0_u8, |mask, (dir, _)| mask | 1 << dir as u8);
+    mask.write_bytes(writer)?;
+    for value in node.data.iter() {
+        value.write_bytes(writer)?;
+    }
+    for (_, child) in node.children_present() {
+        write_node(child, writer)?;
+    }
+    Ok(())
+}
+
+// `depth` is the length of the index path of the node, bounded so that malformed input
+// can't recurse deeper than any valid tree.
+fn read_node<T: FromBytes + Clone, R: Read>(reader: &mut R, depth: u8) -> io::Result<Node<T>> {
+    if depth >= IndexPath::MAX_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Chunk nested deeper than an index path can address"));
+    }
+    let mask = u8::read_bytes(reader)?;
+    let mut values = Vec::with_capacity(8);
+    for _ in 0..8 {
+        values.push(T::read_bytes(reader)?);
+    }
+    let mut node = Node {
+        children: Box::new(Direction::map(|_| None)),
+        data: Direction::map(|dir| values[dir as usize].clone()),
+    };
+    for (dir, child) in node.children.enumerate_mut() {
+        if mask & (1 << dir as u8) != 0 {
+            *child = Some(read_node(reader, depth + 1)?);
+        }
+    }
+    Ok(node)
+}
+
+impl<T: ToBytes, M> Chunk<T, M> {
+    /// Write the values of the tree. Metadata and the mesh style aren't included.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_node(&self.root, writer)
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes).unwrap(); // Writing into a Vec never fails
+        bytes
+    }
+}
+
+impl<T: FromBytes + Clone, M> Chunk<T, M> {
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(Chunk {
+            root: read_node(reader, 0)?,
+            meta: HashMap::new(),
+            mesh_style: MeshStyle::default(),
+        })
+    }
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Self::read_from(&mut &bytes[..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_bytes() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::new().push(Direction::FrontRightBottom), 0x0102);
+        chunk.set(IndexPath::new().push(Direction::RearRightTop).push(Direction::FrontLeftBottom), 0x0304);
+        let bytes = chunk.to_bytes();
+
+        // The layout is fixed regardless of the native endianness
+        let mut expected = vec![0b0000_0001];
+        expected.extend_from_slice(&[0, 0, 0x02, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        expected.push(0);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x04, 0x03]);
+        assert_eq!(bytes, expected);
+
+        let decoded: Chunk<u16> = Chunk::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(*decoded.get(IndexPath::new().push(Direction::FrontRightBottom)), 0x0102);
+        assert_eq!(*decoded.get(IndexPath::new().push(Direction::RearRightTop).push(Direction::FrontLeftBottom)), 0x0304);
+
+        assert!(Chunk::<u16>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_chunk_bytes_too_deep() {
+        // A chain of nodes subdivided in their first octant, `nodes` long
+        let chain = |nodes: u8| {
+            let mut bytes = Vec::new();
+            for i in 0..nodes {
+                bytes.push((i + 1 < nodes) as u8);
+                bytes.extend_from_slice(&[0; 8]);
+            }
+            bytes
+        };
+        assert!(Chunk::<u8>::from_bytes(&chain(IndexPath::MAX_SIZE)).is_ok());
+        let error = Chunk::<u8>::from_bytes(&chain(IndexPath::MAX_SIZE + 1)).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // Fully subdivided masks all the way down are rejected before running out of input
+        let error = Chunk::<u8>::from_bytes(&[0xff; 4096]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_density_voxel_bytes() {
+        let mut bytes = Vec::new();
+        DensityVoxel::new(-2, 0x0a0b).write_bytes(&mut bytes).unwrap();
+        assert_eq!(bytes, vec![0xfe, 0x0b, 0x0a]);
+        assert_eq!(DensityVoxel::read_bytes(&mut bytes.as_slice()).unwrap(), DensityVoxel::new(-2, 0x0a0b));
+    }
+}
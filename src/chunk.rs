@@ -1,24 +1,75 @@
+use std::collections::HashMap;
+use std::hash::Hash;
 use crate::node::Node;
 use crate::voxel::Voxel;
 use crate::index_path::IndexPath;
-use crate::bounds::Bounds;
+use crate::bounds::{Bounds, BoundsSpacialRelationship};
+use crate::grid::Grid;
+use crate::aabb::Aabb;
+use crate::mesher::MeshStyle;
+use crate::direction::{Axis, Direction, DirectionMapper, Edge, Face};
+use crate::{Lerp, VoxelData};
+use glam as math;
 
-pub struct Chunk<T> {
-    pub(crate) root: Node<T>
+/// Solid `[start, end)` ranges of cells along a column.
+pub type Spans = Vec<(u32, u32)>;
+/// Columns keyed by their cell on the two axes across them, as returned by `Chunk::solid_spans`.
+pub type SolidSpans = Vec<((u32, u32), Spans)>;
+
+#[derive(Clone)]
+pub struct Chunk<T, M = ()> {
+    pub(crate) root: Node<T>,
+    // Per-node user data, kept in sync with the tree structure
+    pub(crate) meta: HashMap<IndexPath, M>,
+    // Kept apart from `meta`, whose type is up to the user and which collapses with the nodes
+    pub(crate) mesh_style: MeshStyle,
+}
+
+impl<T: Default + Clone + PartialEq, M> Default for Chunk<T, M> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<T: Default + Copy + PartialEq> Chunk<T> {
-    pub fn new() -> Chunk<T> {
+impl<T: Default + Clone + PartialEq, M> Chunk<T, M> {
+    pub fn new() -> Chunk<T, M> {
         Chunk {
-            root: Node::new_all(Default::default())
+            root: Node::new_all(Default::default()),
+            meta: HashMap::new(),
+            mesh_style: MeshStyle::default(),
         }
     }
 }
 
-impl<T> Chunk<T> {
+impl<T, M> Chunk<T, M> {
     pub fn get(&self, index_path: IndexPath) -> &T {
         self.root.get(index_path)
     }
+    /// Which mesher `World::build_mesh` builds this chunk with.
+    pub fn mesh_style(&self) -> MeshStyle {
+        self.mesh_style
+    }
+    pub fn set_mesh_style(&mut self, style: MeshStyle) {
+        self.mesh_style = style;
+    }
+    /// Value of the coarsest leaf containing `bounds`, descending while a single octant contains it.
+    /// Returns None when the bounds span several octants of a subdivided node, as no single
+    /// value covers them, or when they aren't inside the chunk.
+    pub fn value_at_bounds(&self, bounds: &Bounds) -> Option<&T> {
+        let mut node = &self.root;
+        let mut node_bounds = Bounds::new();
+        loop {
+            let (dir, child) = node.children.enumerate()
+                .find(|(dir, _)| matches!(node_bounds.half(*dir).intersects(bounds), BoundsSpacialRelationship::Contain))?;
+            match child {
+                Some(child) => {
+                    node = child;
+                    node_bounds = node_bounds.half(dir);
+                }
+                None => return Some(&node.data[dir]),
+            }
+        }
+    }
     pub fn get_root(&self) -> Voxel<T> {
         Voxel {
             node: &self.root,
@@ -26,10 +77,1080 @@ impl<T> Chunk<T> {
             bounds: Bounds::new(),
         }
     }
+    /// The leaf containing the index path, which may be coarser than the path itself.
+    /// An empty path returns the root.
+    pub fn get_voxel(&self, index_path: IndexPath) -> Voxel<'_, T> {
+        let mut voxel = self.get_root();
+        for dir in index_path {
+            if !voxel.is_root() && voxel.is_leaf() {
+                break;
+            }
+            voxel = voxel.get_child(dir);
+        }
+        voxel
+    }
+    /// Value of the leaf containing the point, in chunk units.
+    /// Returns None for points outside of the chunk.
+    pub fn sample_point(&self, point: math::Vec3A) -> Option<&T> {
+        self.sample_point_at(point, u8::MAX)
+    }
+    /// Same as `sample_point`, but looking no deeper than `lod` subdivisions, the way
+    /// `Grid::new` does. Subdivided voxels at that depth give the value stored on them.
+    pub fn sample_point_at(&self, point: math::Vec3A, lod: u8) -> Option<&T> {
+        let outside = |v: f32| !(0.0..1.0).contains(&v);
+        if outside(point.x()) || outside(point.y()) || outside(point.z()) {
+            return None;
+        }
+        let mut node = &self.root;
+        let mut point = point;
+        let mut depth = 1;
+        loop {
+            // Position within the node, scaled so that each octant is a unit cube
+            point *= 2.0;
+            let x = (point.x() >= 1.0) as u8;
+            let y = (point.y() >= 1.0) as u8;
+            let z = (point.z() >= 1.0) as u8;
+            point -= math::Vec3A::new(x as f32, y as f32, z as f32);
+            let dir = Direction::from_coords(x, y, z);
+            match &node.children[dir] {
+                Some(child) if depth < lod => node = child,
+                _ => return Some(&node.data[dir]),
+            }
+            depth += 1;
+        }
+    }
+    /// Depth-first traversal calling `on_enter` before and `on_leave` after visiting each voxel,
+    /// so the calls nest like the tree. Starts with the root, and leaves are left right after entering.
+    pub fn walk<E, L>(&self, mut on_enter: E, mut on_leave: L)
+        where E: FnMut(IndexPath, &Bounds), L: FnMut(IndexPath, &Bounds) {
+        fn visit<T, E, L>(node: &Node<T>, index_path: IndexPath, bounds: &Bounds, on_enter: &mut E, on_leave: &mut L)
+            where E: FnMut(IndexPath, &Bounds), L: FnMut(IndexPath, &Bounds) {
+            for (dir, child) in node.children.enumerate() {
+                let child_path = index_path.put(dir);
+                let child_bounds = bounds.half(dir);
+                on_enter(child_path, &child_bounds);
+                if let Some(child) = child {
+                    visit(child, child_path, &child_bounds, on_enter, on_leave);
+                }
+                on_leave(child_path, &child_bounds);
+            }
+        }
+        let bounds = Bounds::new();
+        on_enter(IndexPath::new(), &bounds);
+        visit(&self.root, IndexPath::new(), &bounds, &mut on_enter, &mut on_leave);
+        on_leave(IndexPath::new(), &bounds);
+    }
+    /// Line segments along the edges of the root and every voxel no deeper than `max_depth`,
+    /// in chunk units, for drawing the structure of the tree. Edges shared by neighboring
+    /// voxels are repeated.
+    pub fn debug_grid_mesh(&self, max_depth: u8) -> Vec<(math::Vec3, math::Vec3)> {
+        let mut segments = Vec::new();
+        self.walk(
+            |index_path, bounds| {
+                if index_path.len() > max_depth {
+                    return;
+                }
+                for edge in Edge::all() {
+                    let (a, b) = edge.vertices();
+                    segments.push((math::Vec3::from(bounds.corner(a)), math::Vec3::from(bounds.corner(b))));
+                }
+            },
+            |_, _| {},
+        );
+        segments
+    }
+    /// Depth of the deepest leaf in the chunk.
+    pub fn max_depth(&self) -> u8 {
+        self.root.depth()
+    }
+    /// Depth of the deepest leaf within the region of the index path.
+    /// A path running past a leaf resolves to the depth of that leaf,
+    /// and the empty path gives the same result as `max_depth`.
+    pub fn depth_at(&self, index_path: IndexPath) -> u8 {
+        let mut node = &self.root;
+        let mut depth = 0;
+        for dir in index_path {
+            depth += 1;
+            match &node.children[dir] {
+                Some(child) => node = child,
+                None => return depth,
+            }
+        }
+        depth + node.depth()
+    }
+    pub fn get_meta(&self, index_path: IndexPath) -> Option<&M> {
+        self.meta.get(&index_path)
+    }
+    /// Attach metadata to the node on the index path. The metadata is dropped
+    /// once the path no longer exists, e.g. when its parent collapses on `set`.
+    pub fn set_meta(&mut self, index_path: IndexPath, meta: M) -> Option<M> {
+        assert!(self.root.contains(index_path), "Trying to attach metadata to a path that doesn't exist");
+        self.meta.insert(index_path, meta)
+    }
+    pub fn remove_meta(&mut self, index_path: IndexPath) -> Option<M> {
+        self.meta.remove(&index_path)
+    }
+    /// Release the excess capacity of the metadata table, e.g. after a `reset`.
+    /// Returns an estimate of the bytes reclaimed.
+    pub fn shrink_to_fit(&mut self) -> usize {
+        let capacity = self.meta.capacity();
+        self.meta.shrink_to_fit();
+        (capacity - self.meta.capacity()) * std::mem::size_of::<(IndexPath, M)>()
+    }
+}
+
+impl<T: PartialEq, M> Chunk<T, M> {
+    /// The value of the whole chunk when the root isn't subdivided and all octants are equal.
+    pub fn uniform_value(&self) -> Option<&T> {
+        if self.root.children.iter().all(|child| child.is_none())
+            && self.root.data.data.windows(2).all(|w| w[0] == w[1]) {
+            Some(&self.root.data.data[0])
+        } else {
+            None
+        }
+    }
+    pub fn is_uniform(&self) -> bool {
+        self.uniform_value().is_some()
+    }
 }
 
-impl<T: Copy + PartialEq> Chunk<T> {
+impl<T: Clone + PartialEq, M> Chunk<T, M> {
     pub fn set(&mut self, index_path: IndexPath, value: T) {
-        self.root.set(index_path, value)
+        self.root.set(index_path, value);
+        if !self.meta.is_empty() {
+            let root = &self.root;
+            self.meta.retain(|index_path, _| root.contains(*index_path));
+        }
+    }
+    /// Merge all uniform subtrees. Only needed after modifications bypassing `set`.
+    pub fn canonicalize(&mut self) {
+        self.root.canonicalize();
+        if !self.meta.is_empty() {
+            let root = &self.root;
+            self.meta.retain(|index_path, _| root.contains(*index_path));
+        }
+    }
+    /// Make the whole chunk `value`, dropping all subdivisions and metadata.
+    /// The root node and the metadata table keep their allocations.
+    pub fn reset(&mut self, value: T) {
+        for child in self.root.children.data.iter_mut() {
+            *child = None;
+        }
+        for data in self.root.data.data.iter_mut() {
+            *data = value.clone();
+        }
+        self.meta.clear();
+    }
+    /// Apply at most `budget` patches, leaving the rest in the iterator for a later call.
+    /// Patches arriving on a channel can be applied without blocking with `receiver.try_iter()`.
+    /// Returns the number of patches applied.
+    pub fn apply_available<I>(&mut self, patches: &mut I, budget: usize) -> usize
+        where I: Iterator<Item = (IndexPath, T)> {
+        let mut applied = 0;
+        for (index_path, value) in patches.take(budget) {
+            self.set(index_path, value);
+            applied += 1;
+        }
+        applied
+    }
+}
+
+impl<T: Default + Clone + PartialEq, M> Chunk<T, M> {
+    /// Split the chunk into the eight octants of its root, each scaled up into a chunk of its own.
+    /// Metadata isn't carried over.
+    pub fn split(&self) -> DirectionMapper<Chunk<T>> {
+        Direction::map(|dir| {
+            let mut chunk = Chunk {
+                root: match &self.root.children[dir] {
+                    Some(child) => child.clone(),
+                    None => Node::new_all(self.root.data[dir].clone()),
+                },
+                meta: HashMap::new(),
+                mesh_style: self.mesh_style,
+            };
+            chunk.canonicalize();
+            chunk
+        })
+    }
+    /// Shift the content by a number of cells on a grid subdivided `depth` times.
+    /// Vacated cells take the default value and content shifted out of the chunk is discarded.
+    /// Detail deeper than `depth` is lost, and metadata isn't carried over.
+    pub fn translate(&self, offset: (i32, i32, i32), depth: u8) -> Chunk<T> {
+        let source = Grid::new(self, depth);
+        let mut shifted = Grid::filled(depth, T::default());
+        let size = source.get_size() as i64;
+        for ((x, y, z), value) in source.iter() {
+            let target = (x as i64 + offset.0 as i64, y as i64 + offset.1 as i64, z as i64 + offset.2 as i64);
+            if target.0 >= 0 && target.1 >= 0 && target.2 >= 0 && target.0 < size && target.1 < size && target.2 < size {
+                shifted[(target.0 as usize, target.1 as usize, target.2 as usize)] = value.clone();
+            }
+        }
+        let mut chunk = Chunk::from_grid(&shifted);
+        chunk.mesh_style = self.mesh_style;
+        chunk
+    }
+    /// The layer of cells touching `face`, on a grid subdivided `lod` times.
+    /// The cells are indexed with `u * size + v`, where u and v are the two axes
+    /// parallel to the face in x, y, z order, same as `project`.
+    pub fn boundary_slice(&self, face: Face, lod: u8) -> Vec<T> {
+        let grid = Grid::new(self, lod);
+        let size = grid.get_size();
+        let layer = match face {
+            Face::Left | Face::Front | Face::Bottom => 0,
+            Face::Right | Face::Rear | Face::Top => size - 1,
+        };
+        let mut slice = Vec::with_capacity(size * size);
+        for u in 0..size {
+            for v in 0..size {
+                let cell = match face {
+                    Face::Left | Face::Right => (layer, u, v),
+                    Face::Front | Face::Rear => (u, layer, v),
+                    Face::Bottom | Face::Top => (u, v, layer),
+                };
+                slice.push(grid[cell].clone());
+            }
+        }
+        slice
+    }
+}
+
+impl<T: Default + Clone + PartialEq> Chunk<T> {
+    /// Build a chunk from eight chunks scaled down into its octants. Inverse of `split`.
+    pub fn from_octants(mut octants: DirectionMapper<Chunk<T>>) -> Chunk<T> {
+        let mut chunk = Chunk::new();
+        for (dir, octant) in octants.enumerate_mut() {
+            match octant.uniform_value() {
+                Some(value) => chunk.root.data[dir] = value.clone(),
+                None => {
+                    // The value on a subdivided octant is never read
+                    chunk.root.data[dir] = octant.root.data.data[0].clone();
+                    let root = std::mem::replace(&mut octant.root, Node::new_all(T::default()));
+                    chunk.root.children[dir] = Some(root);
+                }
+            }
+        }
+        chunk.canonicalize();
+        chunk
+    }
+    /// Build a chunk from point samples in chunk units, each setting the cell at `depth` it falls into.
+    /// Points outside of the chunk are skipped, and later points override earlier ones in the same cell.
+    pub fn from_points<I>(points: I, depth: u8) -> Chunk<T>
+        where I: IntoIterator<Item = (math::Vec3A, T)> {
+        let mut chunk = Chunk::new();
+        for (point, value) in points {
+            if let Some(index_path) = IndexPath::locate(point, depth) {
+                chunk.root.set_unmerged(index_path, value);
+            }
+        }
+        chunk.canonicalize();
+        chunk
+    }
+}
+
+impl<T, M> Chunk<T, M> {
+    /// Leaves overlapping the box, in chunk units. Subtrees outside of the box aren't visited.
+    pub fn iter_leaf_in(&self, region: &Aabb) -> impl Iterator<Item = Voxel<'_, T>> {
+        fn visit<'a, T>(node: &'a Node<T>, index_path: IndexPath, bounds: Bounds, region: &Aabb, out: &mut Vec<Voxel<'a, T>>) {
+            for (dir, child) in node.children.enumerate() {
+                let child_bounds = bounds.half(dir);
+                let min = child_bounds.get_position();
+                let aabb = Aabb::new(min, min + math::Vec3A::splat(child_bounds.get_width()));
+                if !aabb.intersects(region) {
+                    continue;
+                }
+                match child {
+                    Some(child) => visit(child, index_path.put(dir), child_bounds, region, out),
+                    None => out.push(Voxel {
+                        node,
+                        index_path: index_path.put(dir),
+                        bounds: child_bounds,
+                    }),
+                }
+            }
+        }
+        let mut voxels = Vec::new();
+        visit(&self.root, IndexPath::new(), Bounds::new(), region, &mut voxels);
+        voxels.into_iter()
+    }
+}
+
+impl<T: Lerp, M> Chunk<T, M> {
+    /// Blend of the values at the point seen at `lod_a` and `lod_b`, going from the first at
+    /// `t = 0` to the second at `t = 1`, for geomorphing between two levels of detail.
+    /// The point is clamped to the chunk, so that samples on its far boundary stay valid.
+    pub fn sample_blended(&self, point: math::Vec3A, lod_a: u8, lod_b: u8, t: f32) -> T {
+        let point = point
+            .max(math::Vec3A::zero())
+            .min(math::Vec3A::splat(1.0 - f32::EPSILON));
+        let a = self.sample_point_at(point, lod_a).unwrap();
+        let b = self.sample_point_at(point, lod_b).unwrap();
+        a.lerp(b, t)
+    }
+}
+
+impl<T: Hash + Eq, M> Chunk<T, M> {
+    /// Iterates the voxels no deeper than `depth`, along with their dominant material.
+    /// Voxels subdivided beyond `depth` take the value covering most of their volume.
+    pub fn iter_lod(&self, depth: u8) -> impl Iterator<Item = (Voxel<'_, T>, &T)> {
+        fn visit<'a, T: Hash + Eq>(node: &'a Node<T>, index_path: IndexPath, bounds: Bounds, depth: u8, out: &mut Vec<(Voxel<'a, T>, &'a T)>) {
+            for (dir, child) in node.children.enumerate() {
+                let voxel = Voxel {
+                    node,
+                    index_path: index_path.put(dir),
+                    bounds: bounds.half(dir),
+                };
+                match child {
+                    Some(child) if voxel.index_path.len() < depth => {
+                        visit(child, voxel.index_path, voxel.bounds, depth, out)
+                    }
+                    Some(child) => out.push((voxel, child.dominant_material())),
+                    None => out.push((voxel, &node.data[dir])),
+                }
+            }
+        }
+        let mut voxels = Vec::new();
+        visit(&self.root, IndexPath::new(), Bounds::new(), depth, &mut voxels);
+        voxels.into_iter()
+    }
+}
+
+impl<T: VoxelData, M> Chunk<T, M> {
+    /// Whether every voxel in the chunk is empty.
+    /// This is O(1) when the root isn't subdivided.
+    pub fn is_empty(&self) -> bool {
+        if self.root.children.iter().all(|child| child.is_none()) {
+            self.root.data.iter().all(|value| value.is_empty())
+        } else {
+            self.iter_leaf().all(|voxel| voxel.get_value().is_empty())
+        }
+    }
+    /// Step along the ray from `origin` towards `dir`, sampling every `step` in chunk units,
+    /// until a solid leaf is found. Returns the first sample position inside the leaf and its value.
+    /// Cheaper to write than a proper DDA traversal, but may step over features thinner than `step`.
+    pub fn march(&self, origin: math::Vec3A, dir: math::Vec3A, step: f32, max_steps: usize) -> Option<(math::Vec3A, &T)> {
+        let dir = dir.normalize();
+        (0..=max_steps)
+            .map(|i| origin + dir * (step * i as f32))
+            .filter_map(|point| self.sample_point(point).map(|value| (point, value)))
+            .find(|(_, value)| !value.is_empty())
+    }
+    /// First solid leaf hit by the ray from `origin` towards `dir`, no further than `max_dist`.
+    /// Returns the leaf along with the point where the ray enters it, all in chunk units.
+    /// Unlike `march`, the leaves are visited exactly, front to back.
+    /// Returns None when `dir` has no length.
+    pub fn raycast(&self, origin: math::Vec3A, dir: math::Vec3A, max_dist: f32) -> Option<(Voxel<'_, T>, math::Vec3A)> {
+        fn visit<'a, T: VoxelData>(voxel: &Voxel<'a, T>, origin: math::Vec3A, dir: math::Vec3A, max_dist: f32) -> Option<(Voxel<'a, T>, f32)> {
+            // The children crossed by the ray, nearest first, with the others at the end
+            let mut hits: [Option<(Voxel<'a, T>, f32)>; 8] = Default::default();
+            for (i, hit) in hits.iter_mut().enumerate() {
+                let child = voxel.get_child(Direction::from(i as u8));
+                let min = child.get_bounds().get_position();
+                let aabb = Aabb::new(min, min + math::Vec3A::splat(child.size()));
+                if let Some((enter, exit)) = aabb.ray_intersection(origin, dir) {
+                    let enter = enter.max(0.0);
+                    if exit >= enter && enter <= max_dist {
+                        *hit = Some((child, enter));
+                    }
+                }
+            }
+            hits.sort_unstable_by(|a, b| match (a, b) {
+                (Some(a), Some(b)) => a.1.total_cmp(&b.1),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            });
+            for (child, enter) in hits.iter_mut().map_while(Option::take) {
+                if child.is_subdivided() {
+                    if let Some(hit) = visit(&child, origin, dir, max_dist) {
+                        return Some(hit);
+                    }
+                } else if !child.get_value().is_empty() {
+                    return Some((child, enter));
+                }
+            }
+            None
+        }
+        if dir.length_squared() == 0.0 {
+            return None;
+        }
+        let dir = dir.normalize();
+        visit(&self.get_root(), origin, dir, max_dist)
+            .map(|(voxel, dist)| (voxel, origin + dir * dist))
+    }
+    /// Occupancy of the chunk seen along `axis`, on a grid subdivided `depth` times.
+    /// A cell is set when any voxel in its column is solid. The cells are indexed with
+    /// `u * size + v`, where u and v are the two remaining axes in x, y, z order.
+    pub fn project(&self, axis: Axis, depth: u8) -> Vec<bool> {
+        let grid = Grid::new(self, depth);
+        let size = grid.get_size();
+        let mut projection = vec![false; size * size];
+        for ((x, y, z), value) in grid.iter() {
+            if value.is_empty() {
+                continue;
+            }
+            let (u, v) = match axis {
+                Axis::X => (y, z),
+                Axis::Y => (x, z),
+                Axis::Z => (x, y),
+            };
+            projection[u * size + v] = true;
+        }
+        projection
+    }
+    /// Runs of solid cells along `axis`, on a grid subdivided `depth` times.
+    /// Each column is keyed by its cell on the two remaining axes in x, y, z order,
+    /// and lists its solid `[start, end)` ranges. Columns without solid cells are omitted.
+    pub fn solid_spans(&self, axis: Axis, depth: u8) -> SolidSpans {
+        let grid = Grid::new(self, depth);
+        let size = grid.get_size();
+        let mut columns = Vec::new();
+        for u in 0..size {
+            for v in 0..size {
+                let mut spans = Vec::new();
+                let mut start = None;
+                for w in 0..=size {
+                    let solid = w < size && !grid[match axis {
+                        Axis::X => (w, u, v),
+                        Axis::Y => (u, w, v),
+                        Axis::Z => (u, v, w),
+                    }].is_empty();
+                    match (solid, start) {
+                        (true, None) => start = Some(w as u32),
+                        (false, Some(begin)) => {
+                            spans.push((begin, w as u32));
+                            start = None;
+                        }
+                        _ => (),
+                    }
+                }
+                if !spans.is_empty() {
+                    columns.push(((u as u32, v as u32), spans));
+                }
+            }
+        }
+        columns
+    }
+    /// Boxes covering the solid cells of a grid subdivided `depth` times, in chunk units.
+    /// Neighboring solid cells are merged greedily into larger boxes, first along z, then y, then x,
+    /// which keeps the count low without guaranteeing the smallest possible set.
+    pub fn collision_boxes(&self, depth: u8) -> Vec<Aabb> {
+        let grid = Grid::new(self, depth);
+        let size = grid.get_size();
+        let mut covered = vec![false; size * size * size];
+        let index = |x: usize, y: usize, z: usize| (x * size + y) * size + z;
+        let free = |covered: &[bool], x: usize, y: usize, z: usize| {
+            !covered[index(x, y, z)] && !grid[(x, y, z)].is_empty()
+        };
+
+        let mut boxes = Vec::new();
+        for x in 0..size {
+            for y in 0..size {
+                for z in 0..size {
+                    if !free(&covered, x, y, z) {
+                        continue;
+                    }
+                    let mut depth = 1;
+                    while z + depth < size && free(&covered, x, y, z + depth) {
+                        depth += 1;
+                    }
+                    let mut height = 1;
+                    while y + height < size && (z..z + depth).all(|k| free(&covered, x, y + height, k)) {
+                        height += 1;
+                    }
+                    let mut width = 1;
+                    while x + width < size && (y..y + height).all(|j| {
+                        (z..z + depth).all(|k| free(&covered, x + width, j, k))
+                    }) {
+                        width += 1;
+                    }
+                    for i in x..x + width {
+                        for j in y..y + height {
+                            for k in z..z + depth {
+                                covered[index(i, j, k)] = true;
+                            }
+                        }
+                    }
+                    let cell = |x: usize, y: usize, z: usize| math::Vec3A::new(x as f32, y as f32, z as f32) / size as f32;
+                    boxes.push(Aabb::new(cell(x, y, z), cell(x + width, y + height, z + depth)));
+                }
+            }
+        }
+        boxes
+    }
+}
+
+impl<T: VoxelData + PartialEq, M> Chunk<T, M> {
+    /// Empty every solid leaf whose six sides are covered by solid leaves, keeping only the shell.
+    /// The chunk is considered alone, so leaves touching its border are always kept.
+    /// Removed leaves are set to `T::default()`.
+    pub fn hollow(&mut self) {
+        let depth = self.max_depth();
+        let grid = Grid::new(self, depth);
+        let size = grid.get_size() as u64;
+        let is_solid = |x: u64, y: u64, z: u64| !grid[(x as usize, y as usize, z as usize)].is_empty();
+
+        let interior: Vec<IndexPath> = self.iter_leaf()
+            .filter(|voxel| !voxel.get_value().is_empty())
+            .filter(|voxel| {
+                let (x, y, z) = voxel.get_bounds().get_position_with_gridsize(size);
+                let width = voxel.get_bounds().get_width_with_gridsize(size);
+                // Cells right outside the face, or None when the face is on the border of the chunk
+                let range = |offset: i32, start: u64| match offset {
+                    0 => Some(start..start + width),
+                    1 if start + width < size => Some(start + width..start + width + 1),
+                    -1 if start > 0 => Some(start - 1..start),
+                    _ => None,
+                };
+                Face::ALL.iter().all(|face| {
+                    let offset = face.offset();
+                    match (range(offset.0, x), range(offset.1, y), range(offset.2, z)) {
+                        (Some(xs), Some(ys), Some(zs)) => xs.clone().all(|x| {
+                            ys.clone().all(|y| zs.clone().all(|z| is_solid(x, y, z)))
+                        }),
+                        _ => false,
+                    }
+                })
+            })
+            .map(|voxel| voxel.get_index_path())
+            .collect();
+        for index_path in interior {
+            self.root.set_unmerged(index_path, T::default());
+        }
+        self.canonicalize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::direction::Direction;
+
+    #[test]
+    fn test_meta() {
+        let mut chunk: Chunk<u16, &str> = Chunk::new();
+        let index_path = IndexPath::new().push(Direction::RearLeftTop).push(Direction::FrontRightBottom);
+        chunk.set(index_path, 1);
+        chunk.set_meta(index_path, "selected");
+        chunk.set_meta(IndexPath::new().push(Direction::RearLeftTop), "locked");
+        assert_eq!(chunk.get_meta(index_path), Some(&"selected"));
+
+        // Setting the value back collapses the subdivided node
+        chunk.set(index_path, 0);
+        assert_eq!(chunk.get_meta(index_path), None);
+        assert_eq!(chunk.get_meta(IndexPath::new().push(Direction::RearLeftTop)), Some(&"locked"));
+    }
+
+    #[test]
+    fn test_depth_at() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        let deep = IndexPath::new().put(Direction::RearLeftTop).put(Direction::FrontRightBottom).put(Direction::RearRightTop);
+        let shallow = IndexPath::new().put(Direction::FrontLeftBottom).put(Direction::RearLeftBottom);
+        chunk.set(deep, 1);
+        chunk.set(shallow, 2);
+        assert_eq!(chunk.depth_at(IndexPath::new()), chunk.max_depth());
+        assert_eq!(chunk.depth_at(IndexPath::new()), 3);
+        assert_eq!(chunk.depth_at(IndexPath::new().put(Direction::RearLeftTop)), 3);
+        assert_eq!(chunk.depth_at(deep), 3);
+        assert_eq!(chunk.depth_at(IndexPath::new().put(Direction::FrontLeftBottom)), 2);
+        assert_eq!(chunk.depth_at(shallow.put(Direction::RearRightTop)), 2);
+        // Octants that were never subdivided
+        assert_eq!(chunk.depth_at(IndexPath::new().put(Direction::RearRightTop)), 1);
+        assert_eq!(chunk.depth_at(IndexPath::new().put(Direction::RearRightTop).put(Direction::RearLeftTop)), 1);
+    }
+
+    #[test]
+    fn test_is_empty() {
+        use crate::world::ChunkCoordinates;
+        use crate::world_builder::{WorldBuilder, Isosurface};
+        use crate::density::DensityVoxel;
+
+        let world_builder: WorldBuilder<DensityVoxel, _> = WorldBuilder::new(
+            |_: &ChunkCoordinates, _: &Bounds| Isosurface::Uniform(DensityVoxel::new(-1, 0))
+        );
+        let mut chunk = world_builder.build(&ChunkCoordinates::new());
+        assert!(chunk.root.children.iter().all(|child| child.is_none()));
+        assert!(chunk.is_empty());
+
+        let index_path = IndexPath::new().push(Direction::RearLeftTop).push(Direction::FrontRightBottom);
+        chunk.set(index_path, DensityVoxel::new(1, 0));
+        assert!(!chunk.is_empty());
+        chunk.set(index_path, DensityVoxel::new(-2, 0));
+        assert!(chunk.is_empty());
+    }
+
+    #[test]
+    fn test_uniform_value() {
+        use crate::world::ChunkCoordinates;
+        use crate::world_builder::{WorldBuilder, Isosurface};
+
+        let world_builder: WorldBuilder<u16, _> = WorldBuilder::new(
+            |_: &ChunkCoordinates, _: &Bounds| Isosurface::Uniform(3)
+        );
+        let mut chunk = world_builder.build(&ChunkCoordinates::new());
+        assert_eq!(chunk.uniform_value(), Some(&3));
+        assert!(chunk.is_uniform());
+
+        chunk.set(IndexPath::new().push(Direction::RearLeftTop).push(Direction::FrontRightBottom), 1);
+        assert_eq!(chunk.uniform_value(), None);
+
+        // Octants differ without subdividing the root
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::new().push(Direction::RearLeftTop), 1);
+        assert!(!chunk.is_uniform());
+    }
+
+    #[test]
+    fn test_value_at_bounds() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 0, 0), 2), 1);
+        chunk.set(IndexPath::from_cell((2, 3, 1), 3), 2);
+
+        // A quarter of the chunk on each side, sitting in the subdivided octant
+        let quarter = Bounds::from_discrete_grid((1, 0, 0), 1, 4);
+        assert_eq!(chunk.value_at_bounds(&quarter), Some(&1));
+        let quarter = Bounds::from_discrete_grid((0, 0, 0), 1, 4);
+        assert_eq!(chunk.value_at_bounds(&quarter), Some(&0));
+
+        // Finer than the leaf containing it
+        let eighth = Bounds::from_discrete_grid((3, 1, 0), 1, 8);
+        assert_eq!(chunk.value_at_bounds(&eighth), Some(&1));
+
+        // Equal to the deepest leaf
+        let eighth = Bounds::from_discrete_grid((2, 3, 1), 1, 8);
+        assert_eq!(chunk.value_at_bounds(&eighth), Some(&2));
+
+        // A whole octant that isn't subdivided
+        let half = Bounds::from_discrete_grid((1, 1, 1), 1, 2);
+        assert_eq!(chunk.value_at_bounds(&half), Some(&0));
+
+        // Straddling two octants of the chunk along x
+        let straddling = Bounds::from_discrete_grid((1, 0, 0), 2, 4);
+        assert_eq!(chunk.value_at_bounds(&straddling), None);
+        // Covering the whole subdivided octant, or the whole chunk
+        let octant = Bounds::from_discrete_grid((0, 0, 0), 2, 4);
+        assert_eq!(chunk.value_at_bounds(&octant), None);
+        assert_eq!(chunk.value_at_bounds(&Bounds::new()), None);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut chunk: Chunk<u16, u8> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 2, 3), 4), 1);
+        chunk.set(IndexPath::from_cell((0, 0, 0), 2), 2);
+        chunk.set_meta(IndexPath::from_cell((1, 2, 3), 4), 7);
+        let capacity = chunk.meta.capacity();
+
+        chunk.reset(5);
+        assert_eq!(chunk.uniform_value(), Some(&5));
+        assert_eq!(chunk.max_depth(), 1);
+        assert!(chunk.meta.is_empty());
+        assert_eq!(chunk.meta.capacity(), capacity);
+        assert!(chunk.shrink_to_fit() > 0);
+        assert!(chunk.meta.capacity() < capacity);
+
+        // Behaves the same as a freshly built chunk from here on
+        let mut fresh: Chunk<u16, u8> = Chunk::new();
+        fresh.reset(5);
+        chunk.set(IndexPath::from_cell((3, 3, 3), 2), 6);
+        fresh.set(IndexPath::from_cell((3, 3, 3), 2), 6);
+        assert!(chunk.iter_leaf().map(|voxel| (voxel.get_index_path(), *voxel.get_value()))
+            .eq(fresh.iter_leaf().map(|voxel| (voxel.get_index_path(), *voxel.get_value()))));
+    }
+
+    #[test]
+    fn test_project() {
+        use crate::density::DensityVoxel;
+
+        let mut chunk: Chunk<DensityVoxel> = Chunk::new();
+        for z in 1..4 {
+            chunk.set(IndexPath::from_cell((2, 1, z), 2), DensityVoxel::new(1, 0));
+        }
+
+        // Index of a projected cell, same layout as `project`
+        let idx = |u: usize, v: usize| u * 4 + v;
+        let top_down = chunk.project(Axis::Z, 2);
+        assert_eq!(top_down.len(), 16);
+        assert_eq!(top_down.iter().filter(|cell| **cell).count(), 1);
+        assert!(top_down[idx(2, 1)]);
+
+        let side = chunk.project(Axis::X, 2);
+        assert_eq!(side.iter().filter(|cell| **cell).count(), 3);
+        assert!(!side[idx(1, 0)]);
+        assert!((1..4).all(|z| side[idx(1, z)]));
+    }
+
+    #[test]
+    fn test_solid_spans() {
+        use crate::density::DensityVoxel;
+
+        let solid = DensityVoxel::new(1, 0);
+        let mut chunk: Chunk<DensityVoxel> = Chunk::new();
+        // A slab filling the whole chunk for y = 1
+        for x in 0..4 {
+            for z in 0..4 {
+                chunk.set(IndexPath::from_cell((x, 1, z), 2), solid);
+            }
+        }
+        let spans = chunk.solid_spans(Axis::X, 2);
+        assert_eq!(spans.len(), 4);
+        for (z, (column, ranges)) in spans.iter().enumerate() {
+            assert_eq!(*column, (1, z as u32));
+            assert_eq!(ranges, &vec![(0, 4)]);
+        }
+        let spans = chunk.solid_spans(Axis::Y, 2);
+        assert_eq!(spans.len(), 16);
+        assert!(spans.iter().all(|(_, ranges)| ranges == &vec![(1, 2)]));
+
+        // Split runs within the same column
+        chunk.set(IndexPath::from_cell((1, 1, 2), 2), DensityVoxel::new(-1, 0));
+        let spans = chunk.solid_spans(Axis::X, 2);
+        assert_eq!(spans[2], ((1, 2), vec![(0, 1), (2, 4)]));
+    }
+
+    #[test]
+    fn test_hollow() {
+        use crate::density::DensityVoxel;
+
+        let solid = DensityVoxel::new(1, 0);
+        let mut chunk: Chunk<DensityVoxel> = Chunk::new();
+        // A 6x6x6 cube away from the border of an 8x8x8 grid
+        for x in 1..7 {
+            for y in 1..7 {
+                for z in 1..7 {
+                    chunk.set(IndexPath::from_cell((x, y, z), 3), solid);
+                }
+            }
+        }
+        chunk.hollow();
+        let grid = Grid::new(&chunk, 3);
+        for ((x, y, z), value) in grid.iter() {
+            let inside = |v: usize| (1..7).contains(&v);
+            let shell = inside(x) && inside(y) && inside(z)
+                && [x, y, z].iter().any(|v| *v == 1 || *v == 6);
+            assert_eq!(!value.is_empty(), shell, "at {:?}", (x, y, z));
+        }
+
+        // In a solid chunk, only the fine leaf away from the border is enclosed
+        let mut chunk: Chunk<DensityVoxel> = Chunk::new();
+        chunk.reset(solid);
+        chunk.set(IndexPath::from_cell((0, 0, 0), 2), DensityVoxel::new(2, 0));
+        chunk.hollow();
+        let empty: Vec<_> = chunk.iter_leaf()
+            .filter(|voxel| voxel.get_value().is_empty())
+            .map(|voxel| voxel.get_index_path())
+            .collect();
+        assert_eq!(empty, vec![IndexPath::from_cell((1, 1, 1), 2)]);
+    }
+
+    #[test]
+    fn test_get_voxel() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((3, 0, 1), 3), 1);
+
+        // Deep inside a leaf at depth 1
+        let deep = IndexPath::from_cell((13, 2, 1), 4);
+        let voxel = chunk.get_voxel(deep);
+        assert_eq!(voxel.get_index_path(), IndexPath::from_cell((1, 0, 0), 1));
+        assert_eq!(voxel.depth(), 1);
+        assert_eq!(voxel.get_bounds().get_position_with_gridsize(2), (1, 0, 0));
+        assert_eq!(voxel.size(), 0.5);
+        assert_eq!(*voxel.get_value(), 0);
+
+        // Down to the deepest leaf, and no further
+        let voxel = chunk.get_voxel(IndexPath::from_cell((3, 0, 1), 3).put(Direction::RearRightTop));
+        assert_eq!(voxel.get_index_path(), IndexPath::from_cell((3, 0, 1), 3));
+        assert_eq!(voxel.get_bounds().get_position_with_gridsize(8), (3, 0, 1));
+        assert_eq!(*voxel.get_value(), 1);
+
+        assert!(chunk.get_voxel(IndexPath::new()).is_root());
+    }
+
+    #[test]
+    fn test_collision_boxes() {
+        use crate::density::DensityVoxel;
+
+        let solid = DensityVoxel::new(1, 0);
+        let mut chunk: Chunk<DensityVoxel> = Chunk::new();
+        assert!(chunk.collision_boxes(3).is_empty());
+
+        // A slab two cells thick across the whole chunk
+        for x in 0..8 {
+            for y in 0..8 {
+                for z in 2..4 {
+                    chunk.set(IndexPath::from_cell((x, y, z), 3), solid);
+                }
+            }
+        }
+        let boxes = chunk.collision_boxes(3);
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes[0].min, math::Vec3A::new(0.0, 0.0, 0.25));
+        assert_eq!(boxes[0].max, math::Vec3A::new(1.0, 1.0, 0.5));
+
+        // A notch splits the slab, and the boxes still cover exactly the solid cells
+        chunk.set(IndexPath::from_cell((3, 4, 2), 3), DensityVoxel::default());
+        let boxes = chunk.collision_boxes(3);
+        assert!(boxes.len() > 1);
+        let grid = Grid::new(&chunk, 3);
+        for ((x, y, z), value) in grid.iter() {
+            let center = (math::Vec3A::new(x as f32, y as f32, z as f32) + math::Vec3A::splat(0.5)) / 8.0;
+            let count = boxes.iter().filter(|aabb| aabb.contains_point(center)).count();
+            assert_eq!(count, if value.is_empty() { 0 } else { 1 });
+        }
+    }
+
+    #[test]
+    fn test_debug_grid_mesh() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        let segments = chunk.debug_grid_mesh(0);
+        assert_eq!(segments.len(), 12);
+        assert!(segments.iter().all(|(a, b)| (*b - *a).length() == 1.0));
+        assert_eq!(chunk.debug_grid_mesh(1).len(), 12 + 8 * 12);
+        // Not subdivided any deeper
+        assert_eq!(chunk.debug_grid_mesh(3).len(), 12 + 8 * 12);
+
+        chunk.set(IndexPath::from_cell((1, 2, 3), 2), 1);
+        assert_eq!(chunk.debug_grid_mesh(1).len(), 12 + 8 * 12);
+        let segments = chunk.debug_grid_mesh(2);
+        assert_eq!(segments.len(), 12 + 8 * 12 + 8 * 12);
+        assert_eq!(segments.iter().filter(|(a, b)| (*b - *a).length() == 0.25).count(), 8 * 12);
+    }
+
+    #[test]
+    fn test_walk() {
+        use std::cell::RefCell;
+
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::new().push(Direction::FrontLeftBottom).push(Direction::RearRightTop), 1);
+
+        let events = RefCell::new(Vec::new());
+        chunk.walk(
+            |index_path, bounds| events.borrow_mut().push((true, index_path, bounds.depth())),
+            |index_path, bounds| events.borrow_mut().push((false, index_path, bounds.depth())),
+        );
+        let events = events.into_inner();
+        // The root, eight octants and eight children, each entered and left once
+        assert_eq!(events.len(), 2 * 17);
+        assert_eq!(events[0], (true, IndexPath::new(), 0));
+        assert_eq!(events[events.len() - 1], (false, IndexPath::new(), 0));
+
+        let mut stack = Vec::new();
+        for (enter, index_path, depth) in events.iter().cloned() {
+            assert_eq!(index_path.len(), depth);
+            if enter {
+                stack.push(index_path);
+            } else {
+                assert_eq!(stack.pop(), Some(index_path));
+            }
+        }
+        assert!(stack.is_empty());
+
+        // The children of the subdivided octant nest inside it
+        let subdivided = IndexPath::new().push(Direction::RearRightTop);
+        let enter = events.iter().position(|event| *event == (true, subdivided, 1)).unwrap();
+        assert_eq!(events[enter + 1], (true, IndexPath::new().push(Direction::FrontLeftBottom).push(Direction::RearRightTop), 2));
+        assert_eq!(events[enter + 17], (false, subdivided, 1));
+    }
+
+    #[test]
+    fn test_march() {
+        use crate::density::DensityVoxel;
+
+        // Solid for x >= 0.5
+        let mut chunk: Chunk<DensityVoxel> = Chunk::new();
+        for &dir in &[Direction::FrontRightBottom, Direction::RearRightBottom, Direction::FrontRightTop, Direction::RearRightTop] {
+            chunk.set(IndexPath::new().push(dir), DensityVoxel::new(1, 2));
+        }
+        assert_eq!(chunk.sample_point(math::Vec3A::new(0.75, 0.1, 0.9)), Some(&DensityVoxel::new(1, 2)));
+        assert!(chunk.sample_point(math::Vec3A::new(0.25, 0.1, 0.9)).unwrap().is_empty());
+        assert_eq!(chunk.sample_point(math::Vec3A::new(1.0, 0.1, 0.9)), None);
+
+        let step = 0.01;
+        let origin = math::Vec3A::new(0.105, 0.3, 0.3);
+        let (hit, value) = chunk.march(origin, math::Vec3A::new(2.0, 0.0, 0.0), step, 100).unwrap();
+        assert_eq!(*value, DensityVoxel::new(1, 2));
+        assert!(hit.x() >= 0.5 && hit.x() < 0.5 + step);
+
+        // Marching away from the solid half, or stopping short of it
+        assert!(chunk.march(origin, math::Vec3A::new(-1.0, 0.0, 0.0), step, 100).is_none());
+        assert!(chunk.march(origin, math::Vec3A::new(1.0, 0.0, 0.0), step, 10).is_none());
+    }
+
+    #[test]
+    fn test_raycast() {
+        use crate::EmptyIf;
+
+        let mut chunk: Chunk<EmptyIf<u16>> = Chunk::new();
+        let near = IndexPath::from_cell((2, 1, 1), 2);
+        let far = IndexPath::from_cell((3, 1, 1), 2);
+        chunk.set(near, EmptyIf(1));
+        chunk.set(far, EmptyIf(2));
+        assert_eq!(chunk.get_voxel(near).get_bounds().get_position(), math::Vec3A::new(0.5, 0.25, 0.25));
+
+        let origin = math::Vec3A::new(0.1, 0.3, 0.4);
+        let (voxel, hit) = chunk.raycast(origin, math::Vec3A::new(3.0, 0.0, 0.0), 1.0).unwrap();
+        assert_eq!(*voxel.get_value(), EmptyIf(1));
+        assert_eq!(voxel.get_index_path(), near);
+        assert!((hit - math::Vec3A::new(0.5, 0.3, 0.4)).length() < 1e-6);
+
+        // From the other side
+        let origin = math::Vec3A::new(0.99, 0.3, 0.4);
+        let (voxel, hit) = chunk.raycast(origin, math::Vec3A::new(-1.0, 0.0, 0.0), 1.0).unwrap();
+        assert_eq!(*voxel.get_value(), EmptyIf(2));
+        assert_eq!(hit, origin);
+
+        // Missing, pointing away, or stopping short
+        assert!(chunk.raycast(math::Vec3A::new(0.1, 0.6, 0.4), math::Vec3A::new(1.0, 0.0, 0.0), 1.0).is_none());
+        assert!(chunk.raycast(math::Vec3A::new(0.1, 0.3, 0.4), math::Vec3A::new(-1.0, 0.0, 0.0), 1.0).is_none());
+        assert!(chunk.raycast(math::Vec3A::new(0.1, 0.3, 0.4), math::Vec3A::new(1.0, 0.0, 0.0), 0.3).is_none());
+        // Without a direction
+        assert!(chunk.raycast(math::Vec3A::new(0.6, 0.3, 0.4), math::Vec3A::zero(), 1.0).is_none());
+        // Diagonal ray coming from outside of the chunk
+        let (voxel, _) = chunk.raycast(math::Vec3A::new(0.25, -0.5, 0.375), math::Vec3A::new(0.5, 1.0, 0.0), 2.0).unwrap();
+        assert_eq!(voxel.get_index_path(), near);
+    }
+
+    #[test]
+    fn test_sample_blended() {
+        let mut chunk: Chunk<f32> = Chunk::new();
+        chunk.set(IndexPath::from_cell((0, 0, 0), 1), 4.0);
+        chunk.set(IndexPath::from_cell((1, 1, 1), 2), 8.0);
+        let point = math::Vec3A::splat(0.3);
+        assert_eq!(chunk.sample_point_at(point, 1), Some(&4.0));
+        assert_eq!(chunk.sample_point_at(point, 2), Some(&8.0));
+        assert_eq!(chunk.sample_point(point), Some(&8.0));
+
+        assert_eq!(chunk.sample_blended(point, 1, 2, 0.0), 4.0);
+        assert_eq!(chunk.sample_blended(point, 1, 2, 1.0), 8.0);
+        assert_eq!(chunk.sample_blended(point, 1, 2, 0.5), 6.0);
+        // Neighboring voxel at the finer level keeps the coarse value
+        assert_eq!(chunk.sample_blended(math::Vec3A::splat(0.1), 1, 2, 1.0), 4.0);
+        // On the far boundary of the chunk
+        assert_eq!(chunk.sample_blended(math::Vec3A::splat(1.0), 1, 2, 0.5), 0.0);
+    }
+
+    #[test]
+    fn test_from_points() {
+        let points = vec![
+            (math::Vec3A::new(0.1, 0.1, 0.1), 1),
+            (math::Vec3A::new(0.9, 0.3, 0.6), 2),
+            (math::Vec3A::new(0.55, 0.05, 0.99), 3),
+            (math::Vec3A::new(1.5, 0.5, 0.5), 4),
+            (math::Vec3A::new(0.5, -0.1, 0.5), 5),
+        ];
+        let chunk: Chunk<u16> = Chunk::from_points(points, 3);
+        assert_eq!(*chunk.get(IndexPath::from_cell((0, 0, 0), 3)), 1);
+        assert_eq!(*chunk.get(IndexPath::from_cell((7, 2, 4), 3)), 2);
+        assert_eq!(*chunk.get(IndexPath::from_cell((4, 0, 7), 3)), 3);
+        // The points outside were skipped
+        assert_eq!(chunk.iter_leaf().filter(|voxel| *voxel.get_value() != 0).count(), 3);
+        assert_eq!(chunk.max_depth(), 3);
+
+        // Uniform regions are merged
+        let chunk: Chunk<u16> = Chunk::from_points(vec![(math::Vec3A::new(0.3, 0.3, 0.3), 0)], 3);
+        assert_eq!(chunk.max_depth(), 1);
+    }
+
+    #[test]
+    fn test_boundary_slice() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((3, 1, 2), 2), 1);
+        chunk.set(IndexPath::from_cell((3, 3, 0), 2), 2);
+        chunk.set(IndexPath::from_cell((0, 1, 2), 2), 3);
+
+        // Index of a cell in a slice, same layout as `boundary_slice`
+        let idx = |u: usize, v: usize| u * 4 + v;
+        let grid = Grid::new(&chunk, 2);
+        let right = chunk.boundary_slice(Face::Right, 2);
+        assert_eq!(right.len(), 16);
+        for y in 0..4 {
+            for z in 0..4 {
+                assert_eq!(right[idx(y, z)], grid[(3, y, z)]);
+            }
+        }
+        assert_eq!(right.iter().filter(|value| **value != 0).count(), 2);
+
+        let left = chunk.boundary_slice(Face::Left, 2);
+        assert_eq!(left[idx(1, 2)], 3);
+        let top = chunk.boundary_slice(Face::Top, 2);
+        assert!(top.iter().all(|value| *value == 0));
+        let bottom = chunk.boundary_slice(Face::Bottom, 2);
+        assert_eq!(bottom[idx(3, 3)], 2);
+    }
+
+    #[test]
+    fn test_split() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 2, 3), 3), 1);
+        chunk.set(IndexPath::from_cell((6, 7, 1), 4), 2);
+        chunk.set(IndexPath::from_cell((1, 0, 0), 1), 3);
+
+        let octants = chunk.split();
+        // Both cells are in the first octant, scaled up by one level: (1, 2, 3) at depth 3
+        // becomes (2, 4, 6), and (6, 7, 1) at depth 4 becomes (6, 7, 1) at depth 3
+        assert_eq!(*octants.data[0].get(IndexPath::from_cell((2, 4, 6), 3)), 1);
+        assert_eq!(*octants.data[0].get(IndexPath::from_cell((6, 7, 1), 3)), 2);
+        assert_eq!(octants.data[1].uniform_value(), Some(&3));
+        assert_eq!(octants.data[7].uniform_value(), Some(&0));
+
+        let merged = Chunk::from_octants(octants);
+        assert!(merged.root == chunk.root);
+
+        let mut other: Chunk<u16> = Chunk::new();
+        other.set(IndexPath::from_cell((1, 2, 3), 3), 1);
+        assert!(merged.root != other.root);
+    }
+
+    #[test]
+    fn test_translate() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 1, 1), 3), 5);
+
+        let translated = chunk.translate((1, 0, -1), 3);
+        assert_eq!(*translated.get(IndexPath::from_cell((2, 1, 0), 3)), 5);
+        assert_eq!(*translated.get(IndexPath::from_cell((1, 1, 1), 3)), 0);
+        assert_eq!(translated.iter_leaf().filter(|voxel| *voxel.get_value() == 5).count(), 1);
+
+        let translated = chunk.translate((7, 0, 0), 3);
+        assert_eq!(translated.iter_leaf().filter(|voxel| *voxel.get_value() == 5).count(), 0);
+    }
+
+    #[test]
+    fn test_apply_available() {
+        use std::sync::mpsc::channel;
+
+        let patches: Vec<(IndexPath, u16)> = (0..10)
+            .map(|i| (IndexPath::from_cell((i % 4, i / 4, 1), 2), i as u16 + 1))
+            .collect();
+        let mut expected: Chunk<u16> = Chunk::new();
+        for (index_path, value) in &patches {
+            expected.set(*index_path, *value);
+        }
+
+        let (sender, receiver) = channel();
+        let mut chunk: Chunk<u16> = Chunk::new();
+        for batch in patches.chunks(4) {
+            for patch in batch {
+                sender.send(*patch).unwrap();
+            }
+            // Apply what's available with a budget smaller than the batch
+            while chunk.apply_available(&mut receiver.try_iter(), 3) > 0 {}
+        }
+        assert_eq!(chunk.to_bytes(), expected.to_bytes());
+        assert_eq!(chunk.apply_available(&mut receiver.try_iter(), 3), 0);
+    }
+
+    #[test]
+    fn test_iter_lod() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        for i in 0..6_u8 {
+            chunk.set(IndexPath::new().push(i.into()).push(Direction::FrontLeftBottom), 3);
+        }
+        let voxels: Vec<_> = chunk.iter_lod(1).collect();
+        assert_eq!(voxels.len(), 8);
+        assert!(voxels.iter().all(|(voxel, _)| voxel.get_index_path().len() == 1));
+        assert_eq!(*voxels[0].1, 3);
+        assert!(voxels[1..].iter().all(|(_, value)| **value == 0));
+
+        let voxels: Vec<_> = chunk.iter_lod(2).collect();
+        assert_eq!(voxels.len(), 15);
+        assert_eq!(voxels.iter().filter(|(_, value)| **value == 3).count(), 6);
     }
 }
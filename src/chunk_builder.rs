@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use crate::chunk::Chunk;
+use crate::direction::Direction;
+use crate::index_path::IndexPath;
+use crate::node::Node;
+
+/// Collects values for a chunk and builds it in one go.
+/// This is faster than calling `Chunk::set` for each value when importing many sparse values,
+/// as the tree is built bottom up in a single pass instead of walking down from the root
+/// for every value.
+pub struct ChunkBuilder<T> {
+    values: HashMap<IndexPath, T>,
+}
+
+impl<T> Default for ChunkBuilder<T> {
+    fn default() -> Self {
+        ChunkBuilder {
+            values: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Default + Clone + PartialEq> ChunkBuilder<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Set the value on the index path. Setting the same path again replaces the value.
+    /// Unlike a series of `Chunk::set` calls, the order only matters for identical paths:
+    /// a value always gives way to the values set on paths deeper inside it,
+    /// whether they were set before or after it.
+    pub fn set(&mut self, index_path: IndexPath, value: T) {
+        self.values.insert(index_path, value);
+    }
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    /// Build the chunk.
+    pub fn finish(self) -> Chunk<T> {
+        let mut values: Vec<(IndexPath, T)> = self.values.into_iter().collect();
+        // Root first order puts the values on a node right before the values inside it,
+        // so that each subtree is a contiguous run
+        values.sort_by(|a, b| Ord::cmp(&a.0, &b.0));
+        let mut chunk = Chunk::new();
+        chunk.root = build_node(&values, 0, &T::default());
+        chunk
+    }
+}
+
+/// Build the node at `depth` from the values inside it, sorted in root first order.
+/// Octants without a value of their own take `inherited`.
+fn build_node<T: Clone + PartialEq>(values: &[(IndexPath, T)], depth: u8, inherited: &T) -> Node<T> {
+    let octant = |index_path: &IndexPath| {
+        let mut index_path = *index_path;
+        index_path.nth(depth as usize)
+    };
+    let mut node = Node::new_all(inherited.clone());
+    let mut rest = values;
+    // Values on the node itself have no octant at this depth
+    while let Some((index_path, _)) = rest.first() {
+        if octant(index_path).is_some() {
+            break;
+        }
+        rest = &rest[1..];
+    }
+    while let Some((index_path, _)) = rest.first() {
+        let dir: Direction = octant(index_path).unwrap();
+        let len = rest.iter().position(|(index_path, _)| octant(index_path) != Some(dir)).unwrap_or(rest.len());
+        let (octant_values, remaining) = rest.split_at(len);
+        rest = remaining;
+
+        let mut deeper = octant_values;
+        if let Some((index_path, value)) = octant_values.first() {
+            if index_path.len() == depth + 1 {
+                node.data[dir] = value.clone();
+                deeper = &octant_values[1..];
+            }
+        }
+        if deeper.is_empty() {
+            continue;
+        }
+        let child = build_node(deeper, depth + 1, &node.data[dir]);
+        let uniform = child.children.iter().all(|c| c.is_none())
+            && child.data.data.windows(2).all(|w| w[0] == w[1]);
+        if uniform {
+            node.data[dir] = child.data.data[0].clone();
+        } else {
+            node.children[dir] = Some(child);
+        }
+    }
+    node
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_builder() {
+        let mut builder = ChunkBuilder::new();
+        let mut chunk: Chunk<u16> = Chunk::new();
+        let mut seed: u32 = 7;
+        for _ in 0..200 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let cell = (seed >> 8 & 15, seed >> 12 & 15, seed >> 16 & 15);
+            let value = (seed >> 20 & 1) as u16;
+            builder.set(IndexPath::from_cell(cell, 4), value);
+            chunk.set(IndexPath::from_cell(cell, 4), value);
+        }
+        // A uniform region made of individual cells
+        for x in 8..16 {
+            for y in 8..16 {
+                for z in 8..16 {
+                    builder.set(IndexPath::from_cell((x, y, z), 4), 2);
+                    chunk.set(IndexPath::from_cell((x, y, z), 4), 2);
+                }
+            }
+        }
+
+        let built = builder.finish();
+        let leaves = |chunk: &Chunk<u16>| -> Vec<(IndexPath, u16)> {
+            chunk.iter_leaf().map(|voxel| (voxel.get_index_path(), *voxel.get_value())).collect()
+        };
+        assert_eq!(leaves(&built), leaves(&chunk));
+        assert_eq!(built.to_gpu_nodes().0.len(), chunk.to_gpu_nodes().0.len());
+        assert!(built.root.children[Direction::RearRightTop].is_none());
+    }
+
+    #[test]
+    fn test_deeper_values_win() {
+        let octant = IndexPath::new().put(Direction::FrontRightTop);
+        let inside = octant.put(Direction::RearLeftBottom);
+        let mut builder = ChunkBuilder::new();
+        assert!(builder.is_empty());
+        builder.set(inside, 2u16);
+        builder.set(octant, 1);
+        builder.set(inside.put(Direction::FrontLeftTop), 3);
+        assert_eq!(builder.len(), 3);
+
+        let built = builder.finish();
+        assert_eq!(*built.get(inside.put(Direction::FrontLeftTop)), 3);
+        assert_eq!(*built.get(inside.put(Direction::FrontRightTop)), 2);
+        assert_eq!(*built.get(octant.put(Direction::RearRightBottom)), 1);
+        assert_eq!(*built.get(IndexPath::new().put(Direction::RearLeftTop)), 0);
+        assert_eq!(built.max_depth(), 3);
+
+        // Values matching their surroundings don't subdivide anything
+        let mut builder = ChunkBuilder::default();
+        builder.set(octant, 1u16);
+        builder.set(inside, 1);
+        builder.set(IndexPath::new().put(Direction::RearLeftTop).put(Direction::FrontRightTop), 0);
+        let built = builder.finish();
+        assert_eq!(built.max_depth(), 1);
+        assert_eq!(*built.get(inside), 1);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::chunk::Chunk;
+use crate::node::Node;
+use crate::index_path::IndexPath;
+
+/// Looks up values in a chunk, starting from the node of the previous lookup
+/// instead of the root. Lookups along a coherent path, such as a scanline,
+/// only walk the part of the tree where consecutive paths differ.
+pub struct ChunkCursor<'a, T> {
+    // The nodes from the root down to the current node
+    stack: Vec<&'a Node<T>>,
+    // Path of the current node
+    index_path: IndexPath,
+}
+
+impl<'a, T> ChunkCursor<'a, T> {
+    /// Same as `Chunk::get`.
+    pub fn seek(&mut self, target: IndexPath) -> &'a T {
+        assert!(!target.is_empty());
+        // Go back up to the deepest node shared with the target, which can't be the slot itself
+        let mut common = self.index_path.common_prefix(target);
+        if common.len() == target.len() {
+            common = common.del();
+        }
+        self.stack.truncate(common.len() as usize + 1);
+        self.index_path = common;
+
+        let mut node = *self.stack.last().unwrap();
+        let mut remaining = target;
+        for _ in 0..common.len() {
+            remaining.next();
+        }
+        let mut dir = remaining.next().unwrap();
+        for next in remaining {
+            match &node.children[dir] {
+                Some(child) => {
+                    node = child;
+                    self.stack.push(node);
+                    self.index_path = self.index_path.put(dir);
+                    dir = next;
+                }
+                None => break,
+            }
+        }
+        &node.data[dir]
+    }
+    /// Path of the node the next lookup starts from.
+    pub fn index_path(&self) -> IndexPath {
+        self.index_path
+    }
+}
+
+impl<T, M> Chunk<T, M> {
+    pub fn cursor(&self) -> ChunkCursor<'_, T> {
+        ChunkCursor {
+            stack: vec![&self.root],
+            index_path: IndexPath::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::index_path::IndexPath;
+
+    #[test]
+    fn test_seek() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        for x in 0..16 {
+            chunk.set(IndexPath::from_cell((x, 3, 5), 4), x as u16 + 1);
+        }
+        chunk.set(IndexPath::from_cell((1, 0, 0), 1), 20);
+
+        let mut cursor = chunk.cursor();
+        for x in 0..16 {
+            let index_path = IndexPath::from_cell((x, 3, 5), 4);
+            assert_eq!(cursor.seek(index_path), chunk.get(index_path));
+            // The cursor stays on the node holding the leaf, where the next lookup starts
+            assert_eq!(cursor.index_path(), index_path.del());
+        }
+
+        // Jumping elsewhere, shallower and deeper than the leaves
+        for &(cell, depth) in &[((7, 0, 0), 3), ((0, 0, 0), 1), ((3, 3, 5), 4), ((0, 1, 2), 5), ((3, 3, 5), 4)] {
+            let index_path = IndexPath::from_cell(cell, depth);
+            assert_eq!(cursor.seek(index_path), chunk.get(index_path));
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{Lerp, VoxelData};
+
+/// A ready-to-use voxel with a density and a material.
+/// The voxel is empty when the density is not positive.
+#[derive(Copy, Clone, Default, Debug, Eq, PartialEq, Hash)]
+pub struct DensityVoxel {
+    pub density: i8,
+    pub material: u16,
+}
+
+impl DensityVoxel {
+    pub fn new(density: i8, material: u16) -> Self {
+        DensityVoxel { density, material }
+    }
+}
+
+impl Lerp for DensityVoxel {
+    /// Interpolates the density, taking the material of the closer voxel.
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        DensityVoxel {
+            density: (self.density as f32).lerp(&(other.density as f32), t).round() as i8,
+            material: if t < 0.5 { self.material } else { other.material },
+        }
+    }
+}
+
+impl VoxelData for DensityVoxel {
+    fn is_empty(&self) -> bool {
+        self.density <= 0
+    }
+    fn density(&self) -> f32 {
+        self.density as f32 / i8::MAX as f32
+    }
+    fn material_id(&self) -> u16 {
+        self.material
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bounds::{Bounds, BoundsSpacialRelationship};
+    use crate::world::{World, ChunkCoordinates};
+    use crate::world_builder::{WorldBuilder, Isosurface};
+    use crate::mesher::{Mesher, MarchingCubesMesher};
+
+    #[test]
+    fn test_density_voxel() {
+        assert!(DensityVoxel::default().is_empty());
+        assert!(DensityVoxel::new(-5, 1).is_empty());
+        assert!(!DensityVoxel::new(1, 1).is_empty());
+        assert_eq!(DensityVoxel::new(i8::MAX, 3).density(), 1.0);
+        assert_eq!(DensityVoxel::new(1, 3).material_id(), 3);
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let world_builder: WorldBuilder<DensityVoxel, _> = WorldBuilder::new(
+            |_: &ChunkCoordinates, bounds: &Bounds| {
+                let target_bounds = Bounds::from_discrete_grid((2, 2, 2), 4, 8);
+                match target_bounds.intersects(bounds) {
+                    BoundsSpacialRelationship::Disjoint => Isosurface::Uniform(DensityVoxel::new(-1, 0)),
+                    BoundsSpacialRelationship::Contain => Isosurface::Uniform(DensityVoxel::new(64, 7)),
+                    BoundsSpacialRelationship::Intersect => Isosurface::Surface,
+                }
+            }
+        );
+        let chunk = world_builder.build(&ChunkCoordinates::new());
+        let solid = chunk.iter_leaf().filter(|voxel| !voxel.get_value().is_empty()).count();
+        assert!(solid > 0);
+        assert!(chunk.iter_leaf().filter(|voxel| !voxel.get_value().is_empty()).all(|voxel| voxel.get_value().material_id() == 7));
+
+        let mut world = World::new();
+        world.insert_chunk(ChunkCoordinates::new(), chunk);
+        let mesh = MarchingCubesMesher::new(&world).build(&ChunkCoordinates::new(), 3);
+        assert!(!mesh.indices().is_empty());
+    }
+}
@@ -1,5 +1,5 @@
 use std::ops::{Index, IndexMut};
-use std::mem::MaybeUninit;
+use glam as math;
 //          Cell Corners
 //
 //       6-------------------7
@@ -81,15 +81,60 @@ impl Direction {
             f(Self::RearRightTop),
         ])
     }
+    /// Offsets of the octant along each axis, following the same convention as `is_max_x` etc.
     pub fn breakdown(&self) -> (u8, u8, u8) {
         let val = *self as u8;
-        let z = val & 0b001;
+        let x = val & 0b001;
         let y = (val >> 1) & 0b001;
-        let x = val >> 2;
+        let z = val >> 2;
         (x, y, z)
     }
+    /// Whether the two octants of the same parent touch along a whole face.
+    pub fn shares_face(&self, other: Direction) -> bool {
+        (*self as u8 ^ other as u8).count_ones() == 1
+    }
+    /// Whether the two octants of the same parent touch along an edge only.
+    pub fn shares_edge(&self, other: Direction) -> bool {
+        (*self as u8 ^ other as u8).count_ones() == 2
+    }
+    /// Whether the two octants of the same parent touch at a single corner, being opposite.
+    pub fn shares_corner(&self, other: Direction) -> bool {
+        (*self as u8 ^ other as u8).count_ones() == 3
+    }
+    /// Position of the corner on the unit cube.
+    pub fn corner_offset(&self) -> math::Vec3 {
+        let (x, y, z) = self.breakdown();
+        math::Vec3::new(x as f32, y as f32, z as f32)
+    }
+    /// Unit vector from the center of the cube towards the corner.
+    pub fn normal(&self) -> math::Vec3 {
+        (self.corner_offset() * 2.0 - math::Vec3::one()) / 3.0_f32.sqrt()
+    }
+    /// The three faces of the cube meeting at the corner, in x, y, z order.
+    pub fn faces(&self) -> [Face; 3] {
+        [
+            if self.is_max_x() { Face::Right } else { Face::Left },
+            if self.is_max_y() { Face::Rear } else { Face::Front },
+            if self.is_max_z() { Face::Top } else { Face::Bottom },
+        ]
+    }
+    /// Octant with the offsets along each axis. Inverse of `breakdown`.
+    /// Panics when an offset isn't 0 or 1.
+    pub fn from_coords(x: u8, y: u8, z: u8) -> Self {
+        Self::try_from_coords(x, y, z).expect("Octant offsets must be 0 or 1")
+    }
+    pub fn try_from_coords(x: u8, y: u8, z: u8) -> Result<Self, InvalidOctantOffset> {
+        if x > 1 || y > 1 || z > 1 {
+            return Err(InvalidOctantOffset((x, y, z)));
+        }
+        Ok((x | y << 1 | z << 2).into())
+    }
 }
 
+/// Offsets passed to `Direction::try_from_coords` other than 0 or 1.
+#[derive(Debug, Eq, PartialEq)]
+pub struct InvalidOctantOffset(pub (u8, u8, u8));
+
 impl From<u8> for Direction {
     fn from(val: u8) -> Self {
         let val = val & 0b111;
@@ -107,6 +152,61 @@ impl From<u8> for Direction {
     }
 }
 
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Face {
+    Left = 0,   // -x
+    Right = 1,  // +x
+    Front = 2,  // -y
+    Rear = 3,   // +y
+    Bottom = 4, // -z
+    Top = 5,    // +z
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::Left, Face::Right, Face::Front, Face::Rear, Face::Bottom, Face::Top];
+
+    #[inline]
+    pub fn opposite(&self) -> Self {
+        Self::ALL[*self as usize ^ 1]
+    }
+
+    /// Unit step towards the neighbor sharing this face.
+    pub fn offset(&self) -> (i32, i32, i32) {
+        match self {
+            Face::Left => (-1, 0, 0),
+            Face::Right => (1, 0, 0),
+            Face::Front => (0, -1, 0),
+            Face::Rear => (0, 1, 0),
+            Face::Bottom => (0, 0, -1),
+            Face::Top => (0, 0, 1),
+        }
+    }
+
+    /// Whether the octant lies on this side of its parent.
+    pub fn contains(&self, dir: Direction) -> bool {
+        match self {
+            Face::Left => dir.is_min_x(),
+            Face::Right => dir.is_max_x(),
+            Face::Front => dir.is_min_y(),
+            Face::Rear => dir.is_max_y(),
+            Face::Bottom => dir.is_min_z(),
+            Face::Top => dir.is_max_z(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+}
+
 #[derive(Clone, Default)]
 pub struct DirectionMapper<T> {
     pub data: [T; 8]
@@ -145,6 +245,59 @@ impl<'a, T> Iterator for DirectionMapperEnumeratorMut<'a, T> {
     }
 }
 
+/// Owning iterator over the values of a `DirectionMapper`, in octant order.
+pub struct DirectionMapperIntoIter<T> {
+    data: [Option<T>; 8],
+    index: u8,
+}
+
+impl<T> Iterator for DirectionMapperIntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= 8 {
+            None
+        } else {
+            let val = self.data[self.index as usize].take();
+            self.index += 1;
+            val
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = 8 - self.index as usize;
+        (len, Some(len))
+    }
+}
+
+impl<T> ExactSizeIterator for DirectionMapperIntoIter<T> {}
+
+pub struct DirectionMapperIntoEnumerator<T> {
+    inner: DirectionMapperIntoIter<T>,
+}
+
+impl<T> Iterator for DirectionMapperIntoEnumerator<T> {
+    type Item = (Direction, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        let dir = Direction::from(self.inner.index);
+        self.inner.next().map(|val| (dir, val))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for DirectionMapperIntoEnumerator<T> {}
+
+impl<T> IntoIterator for DirectionMapper<T> {
+    type Item = T;
+    type IntoIter = DirectionMapperIntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        DirectionMapperIntoIter {
+            data: self.into_map(Some).data,
+            index: 0,
+        }
+    }
+}
+
 impl<T> DirectionMapper<T> {
     pub fn iter(&self) -> std::slice::Iter<T> {
         self.data.iter()
@@ -164,17 +317,61 @@ impl<T> DirectionMapper<T> {
     }
 
 
+    /// Same as `enumerate`, but consumes the values.
+    pub fn into_enumerate(self) -> DirectionMapperIntoEnumerator<T> {
+        DirectionMapperIntoEnumerator {
+            inner: self.into_iter(),
+        }
+    }
+
     pub fn new(data: [T; 8]) -> Self {
         DirectionMapper { data }
     }
 
+    /// A mapper holding `f(&self[dir])` for each direction.
+    pub fn map<U, F>(&self, f: F) -> DirectionMapper<U>
+        where F: Fn(&T) -> U {
+        Direction::map(|dir| f(&self[dir]))
+    }
+
+    /// Same as `map`, but consumes the values.
+    pub fn into_map<U, F>(self, mut f: F) -> DirectionMapper<U>
+        where F: FnMut(T) -> U {
+        let [a, b, c, d, e, g, h, i] = self.data;
+        DirectionMapper::new([f(a), f(b), f(c), f(d), f(e), f(g), f(h), f(i)])
+    }
+
+    /// Pair up the values of both mappers, so that `zipped[dir] == (self[dir], other[dir])`.
+    pub fn zip<U>(self, other: DirectionMapper<U>) -> DirectionMapper<(T, U)> {
+        self.zip_with(other, |a, b| (a, b))
+    }
+
+    /// Combine the values of both mappers with `f`, direction by direction.
+    pub fn zip_with<U, V, F>(self, other: DirectionMapper<U>, mut f: F) -> DirectionMapper<V>
+        where F: FnMut(T, U) -> V {
+        let [a0, a1, a2, a3, a4, a5, a6, a7] = self.data;
+        let [b0, b1, b2, b3, b4, b5, b6, b7] = other.data;
+        DirectionMapper::new([
+            f(a0, b0), f(a1, b1), f(a2, b2), f(a3, b3),
+            f(a4, b4), f(a5, b5), f(a6, b6), f(a7, b7),
+        ])
+    }
+
+    /// Build a mapper holding `mapper(dir)` for each direction, in octant order.
+    /// Unlike `new`, the closure can compute each value from its direction and captured state.
+    ///
+    /// ```
+    /// use octree::bounds::Bounds;
+    /// use octree::direction::{Direction, DirectionMapper};
+    ///
+    /// let parent = Bounds::new();
+    /// let octants = DirectionMapper::from_mapper(|dir| parent.half(dir));
+    /// assert_eq!(octants[Direction::RearRightTop].get_width(), 0.5);
+    /// assert_eq!(octants[Direction::FrontRightBottom].get_position_with_gridsize(2), (1, 0, 0));
+    /// ```
     pub fn from_mapper<FN>(mapper: FN) -> Self
         where FN: Fn(Direction) -> T {
-        let mut data: [T; 8] = unsafe { MaybeUninit::uninit().assume_init() };
-        for i in 0..8_u8 {
-            data[i as usize] = (mapper)(i.into());
-        }
-        Self::new(data)
+        Direction::map(mapper)
     }
 }
 
@@ -262,6 +459,49 @@ impl From<u8> for Edge {
 }
 
 impl Edge {
+    /// All twelve edges of a cell, in the order of their discriminants.
+    pub fn all() -> impl Iterator<Item = Edge> {
+        (0..12).map(Edge::from)
+    }
+
+    /// The edge running between two corners of a cell, in either order.
+    /// Returns None unless the corners differ along exactly one axis.
+    pub fn from_directions(a: Direction, b: Direction) -> Option<Edge> {
+        if !a.shares_face(b) {
+            return None;
+        }
+        Edge::all().find(|edge| {
+            let (start, end) = edge.vertices();
+            (start == a && end == b) || (start == b && end == a)
+        })
+    }
+
+    /// Axis the edge runs along.
+    pub fn axis(&self) -> Axis {
+        let (a, b) = self.vertices();
+        match a as u8 ^ b as u8 {
+            1 => Axis::X,
+            2 => Axis::Y,
+            4 => Axis::Z,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Point on the edge of a unit cell where the field crosses `iso`, given the values
+    /// `v0` and `v1` at the first and second vertex. Falls back to the midpoint when
+    /// both values are equal.
+    pub fn interpolate(&self, v0: f32, v1: f32, iso: f32) -> math::Vec3 {
+        let (a, b) = self.vertices();
+        let (start, end) = (a.corner_offset(), b.corner_offset());
+        let denominator = v1 - v0;
+        let t = if denominator.abs() <= f32::EPSILON {
+            0.5
+        } else {
+            ((iso - v0) / denominator).clamp(0.0, 1.0)
+        };
+        start + (end - start) * t
+    }
+
     pub fn vertices(&self) -> (Direction, Direction) {
         match self {
             Edge::LowerFar => (Direction::RearLeftBottom, Direction::RearRightBottom),
@@ -281,3 +521,149 @@ impl Edge {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_coords() {
+        for i in 0..8 {
+            let dir = Direction::from(i);
+            let (x, y, z) = dir.breakdown();
+            assert_eq!(Direction::from_coords(x, y, z), dir);
+        }
+        assert_eq!(Direction::from_coords(1, 0, 1), Direction::FrontRightTop);
+        assert_eq!(Direction::try_from_coords(0, 2, 0), Err(InvalidOctantOffset((0, 2, 0))));
+    }
+
+    #[test]
+    fn test_mapper_map() {
+        let mapper = Direction::map(|dir| dir as u8 * 2);
+        let mapped = mapper.map(|value| *value as u16 + 1);
+        for i in 0..8 {
+            let dir = Direction::from(i);
+            assert_eq!(mapped[dir], mapper[dir] as u16 + 1);
+        }
+
+        let strings = mapper.map(|value| value.to_string());
+        let mut order = Vec::new();
+        let lengths = strings.into_map(|string| {
+            order.push(string.clone());
+            string.len()
+        });
+        assert_eq!(order, vec!["0", "2", "4", "6", "8", "10", "12", "14"]);
+        assert_eq!(lengths[Direction::RearRightTop], 2);
+        assert_eq!(lengths[Direction::FrontRightTop], 2);
+        assert_eq!(lengths[Direction::RearRightBottom], 1);
+    }
+
+    #[test]
+    fn test_mapper_zip() {
+        let values = Direction::map(|dir| dir as u8);
+        let weights = Direction::map(|dir| dir.corner_offset());
+        let zipped = values.clone().zip(weights.clone());
+        for i in 0..8 {
+            let dir = Direction::from(i);
+            assert_eq!(zipped[dir], (values[dir], weights[dir]));
+        }
+        let weighted = values.zip_with(weights, |value, weight| weight * value as f32);
+        assert_eq!(weighted[Direction::RearRightBottom], math::Vec3::new(3.0, 3.0, 0.0));
+        assert_eq!(weighted[Direction::FrontLeftTop], math::Vec3::new(0.0, 0.0, 4.0));
+    }
+
+    #[test]
+    fn test_mapper_into_iter() {
+        let mapper = Direction::map(|dir| vec![dir as u8; dir as usize]);
+        let mut iter = mapper.clone().into_iter();
+        assert_eq!(iter.len(), 8);
+        assert_eq!(iter.next(), Some(vec![]));
+        assert_eq!(iter.len(), 7);
+        assert_eq!(iter.last(), Some(vec![7; 7]));
+
+        let mut count = 0;
+        for (dir, values) in mapper.into_enumerate() {
+            assert_eq!(values.len(), dir as usize);
+            assert!(values.iter().all(|value| *value == dir as u8));
+            count += 1;
+        }
+        assert_eq!(count, 8);
+    }
+
+    #[test]
+    fn test_edge_from_directions() {
+        assert_eq!(Edge::all().count(), 12);
+        for edge in Edge::all() {
+            let (a, b) = edge.vertices();
+            assert_eq!(Edge::from_directions(a, b), Some(edge));
+            assert_eq!(Edge::from_directions(b, a), Some(edge));
+        }
+        assert_eq!(Edge::from_directions(Direction::RearLeftBottom, Direction::FrontRightTop), None);
+        assert_eq!(Edge::from_directions(Direction::RearLeftBottom, Direction::FrontRightBottom), None);
+        assert_eq!(Edge::from_directions(Direction::RearLeftTop, Direction::RearLeftTop), None);
+    }
+
+    #[test]
+    fn test_edge_axis() {
+        assert_eq!(Edge::all().filter(|edge| edge.axis() == Axis::X).count(), 4);
+        assert_eq!(Edge::all().filter(|edge| edge.axis() == Axis::Y).count(), 4);
+        assert_eq!(Edge::all().filter(|edge| edge.axis() == Axis::Z).count(), 4);
+        assert_eq!(Edge::LowerFar.axis(), Axis::X);
+        assert_eq!(Edge::VerticalFrontLeft.axis(), Axis::Z);
+    }
+
+    #[test]
+    fn test_edge_interpolate() {
+        let edge = Edge::LowerFar;
+        let (a, b) = edge.vertices();
+        let (start, end) = (a.corner_offset(), b.corner_offset());
+        assert_eq!(edge.interpolate(-1.0, 3.0, 0.0), start + (end - start) * 0.25);
+        assert_eq!(edge.interpolate(3.0, -1.0, 0.0), start + (end - start) * 0.75);
+        assert_eq!(edge.interpolate(0.0, 2.0, 2.0), end);
+        // Flat field
+        assert_eq!(edge.interpolate(1.0, 1.0, 0.0), (start + end) * 0.5);
+        for edge in Edge::all() {
+            let (a, b) = edge.vertices();
+            let point = edge.interpolate(-1.0, 1.0, 0.0);
+            assert_eq!(point, (a.corner_offset() + b.corner_offset()) * 0.5);
+        }
+    }
+
+    #[test]
+    fn test_adjacency() {
+        for i in 0..8 {
+            let dir = Direction::from(i);
+            let others = || (0..8).map(Direction::from);
+            assert_eq!(others().filter(|other| dir.shares_face(*other)).count(), 3);
+            assert_eq!(others().filter(|other| dir.shares_edge(*other)).count(), 3);
+            assert_eq!(others().filter(|other| dir.shares_corner(*other)).count(), 1);
+            assert!(dir.shares_corner(dir.opposite()));
+            assert!(!dir.shares_face(dir) && !dir.shares_edge(dir) && !dir.shares_corner(dir));
+        }
+        assert!(Direction::FrontLeftBottom.shares_face(Direction::FrontRightBottom));
+        assert!(Direction::FrontLeftBottom.shares_edge(Direction::RearRightBottom));
+        assert!(!Direction::FrontLeftBottom.shares_edge(Direction::FrontLeftTop));
+    }
+
+    #[test]
+    fn test_corner_normal() {
+        let expected = [
+            (Direction::FrontLeftBottom, (-1.0, -1.0, -1.0), [Face::Left, Face::Front, Face::Bottom]),
+            (Direction::FrontRightBottom, (1.0, -1.0, -1.0), [Face::Right, Face::Front, Face::Bottom]),
+            (Direction::RearLeftBottom, (-1.0, 1.0, -1.0), [Face::Left, Face::Rear, Face::Bottom]),
+            (Direction::RearRightBottom, (1.0, 1.0, -1.0), [Face::Right, Face::Rear, Face::Bottom]),
+            (Direction::FrontLeftTop, (-1.0, -1.0, 1.0), [Face::Left, Face::Front, Face::Top]),
+            (Direction::FrontRightTop, (1.0, -1.0, 1.0), [Face::Right, Face::Front, Face::Top]),
+            (Direction::RearLeftTop, (-1.0, 1.0, 1.0), [Face::Left, Face::Rear, Face::Top]),
+            (Direction::RearRightTop, (1.0, 1.0, 1.0), [Face::Right, Face::Rear, Face::Top]),
+        ];
+        for &(dir, (x, y, z), faces) in &expected {
+            let diagonal = math::Vec3::new(x, y, z);
+            assert_eq!(dir.corner_offset(), (diagonal + math::Vec3::one()) / 2.0);
+            assert!((dir.normal() - diagonal / 3.0_f32.sqrt()).length() < 1e-6);
+            assert!((dir.normal().length() - 1.0).abs() < 1e-6);
+            assert_eq!(dir.faces(), faces);
+            assert!(faces.iter().all(|face| face.contains(dir)));
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::chunk::Chunk;
+use crate::direction::Direction;
+use crate::node::Node;
+
+/// A node of the octree flattened for GPU-side traversal.
+/// Children that are subdivided are stored contiguously in direction order,
+/// starting at `first_child` in the node array.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct GpuNode {
+    pub first_child: u32,
+    pub child_mask: u32, // bit n set when the octant `n` is subdivided
+}
+
+impl GpuNode {
+    pub fn has_child(&self, dir: Direction) -> bool {
+        self.child_mask & (1 << dir as u32) != 0
+    }
+    /// Index of the child node in the flattened array, if the octant is subdivided.
+    pub fn child_index(&self, dir: Direction) -> Option<u32> {
+        if self.has_child(dir) {
+            let preceding = self.child_mask & ((1 << dir as u32) - 1);
+            Some(self.first_child + preceding.count_ones())
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: Clone, M> Chunk<T, M> {
+    /// Flatten the tree breadth-first. The data of node `i` on octant `dir`
+    /// is stored at `i * 8 + dir` in the returned data array.
+    pub fn to_gpu_nodes(&self) -> (Vec<GpuNode>, Vec<T>) {
+        let mut queue: Vec<&Node<T>> = vec![&self.root];
+        let mut nodes = Vec::new();
+        let mut data = Vec::new();
+        let mut i = 0;
+        while i < queue.len() {
+            let node = queue[i];
+            let mut gpu_node = GpuNode {
+                first_child: queue.len() as u32,
+                child_mask: 0,
+            };
+            for (dir, child) in node.children_present() {
+                gpu_node.child_mask |= 1 << dir as u32;
+                queue.push(child);
+            }
+            nodes.push(gpu_node);
+            data.extend(node.data.iter().cloned());
+            i += 1;
+        }
+        (nodes, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index_path::IndexPath;
+
+    fn gpu_get<'a, T>(nodes: &[GpuNode], data: &'a [T], index_path: IndexPath) -> &'a T {
+        let mut index = 0;
+        for dir in index_path {
+            match nodes[index as usize].child_index(dir) {
+                Some(child) => index = child,
+                None => return &data[index as usize * 8 + dir as usize],
+            }
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn test_gpu_nodes() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::new().push(Direction::FrontRightTop).push(Direction::RearLeftBottom), 1);
+        chunk.set(IndexPath::new().push(Direction::RearLeftTop).push(Direction::FrontLeftBottom), 2);
+        chunk.set(IndexPath::new().push(Direction::RearLeftTop).push(Direction::RearRightBottom), 3);
+
+        let (nodes, data) = chunk.to_gpu_nodes();
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(data.len(), nodes.len() * 8);
+        assert_eq!(nodes[0].first_child, 1);
+        assert_eq!(nodes[0].child_mask.count_ones(), 3);
+
+        for i in 0..8_u8 {
+            for j in 0..8_u8 {
+                for k in 0..8_u8 {
+                    let index_path = IndexPath::new().push(i.into()).push(j.into()).push(k.into());
+                    assert_eq!(gpu_get(&nodes, &data, index_path), chunk.get(index_path));
+                }
+            }
+        }
+    }
+}
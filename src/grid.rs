@@ -1,90 +1,263 @@
 use crate::chunk::Chunk;
 use crate::node::Node;
-use crate::direction::DirectionMapper;
-use std::alloc::{alloc, dealloc, Layout};
+use crate::direction::{Direction, DirectionMapper, Face};
+use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
 use std::ops::{Index, IndexMut};
+use std::collections::HashMap;
+use std::ptr::NonNull;
+use crate::{Lerp, VoxelData};
+use crate::mesher::MeshStyle;
+
+/// The grid is coarser than the chunk it was built from, so detail would be lost.
+#[derive(Debug, Eq, PartialEq)]
+pub struct LodTooCoarse {
+    pub lod: u8,
+    pub chunk_depth: u8,
+}
 
 // Because this is a n x n x n array where n is 2^lod,
 // We specify that there's 2^(3*lod) elements in the array.
 // So the array can be indexed by a binary number with 3*lod digits.
 pub struct Grid<T> {
-    data: Box<[T]>,
+    data: NonNull<T>,
+    layout: Layout, // Used for deallocation, as the alignment may exceed the alignment of T
     lod: u8,
 }
 
-impl<T: Default + Clone> Grid<T> {
-    pub fn new(chunk: &Chunk<T>, lod: u8) -> Grid<T> {
+/// The finest lod a grid supports. The cells are indexed by a number with 3 * lod digits,
+/// which must fit into a `usize` without reaching the sign bit. This is lod 21 on 64 bit targets
+/// and lod 10 on 32 bit targets.
+pub const MAX_LOD: u8 = ((std::mem::size_of::<usize>() * 8 - 1) / 3) as u8;
+
+#[inline]
+fn offset(lod: u8, index: (usize, usize, usize)) -> usize {
+    debug_assert!(lod <= MAX_LOD);
+    debug_assert!(index.0 < (1 << lod));
+    debug_assert!(index.1 < (1 << lod));
+    debug_assert!(index.2 < (1 << lod));
+    index.2 | (index.1 << lod) | (index.0 << (2 * lod))
+}
+
+unsafe impl<T: Send> Send for Grid<T> {}
+unsafe impl<T: Sync> Sync for Grid<T> {}
+
+impl<T: Clone> Grid<T> {
+    /// Allocate a grid with every cell set to `value`.
+    fn allocate(lod: u8, align: usize, value: T) -> Grid<T> {
         assert!(lod > 0);
-        let mut grid = Self {
-            data: vec![Default::default(); 1 << (lod * 3)].into_boxed_slice(),
-            lod,
-        };
+        assert!(lod <= MAX_LOD, "Grid lod {} exceeds the maximum of {}", lod, MAX_LOD);
+        let len: usize = 1 << (lod * 3);
+        let layout = Layout::array::<T>(len)
+            .and_then(|layout| layout.align_to(align))
+            .expect("Invalid grid layout");
+        unsafe {
+            let data = if layout.size() == 0 {
+                NonNull::dangling()
+            } else {
+                match NonNull::new(alloc(layout) as *mut T) {
+                    Some(data) => data,
+                    None => handle_alloc_error(layout),
+                }
+            };
+            for i in 0..len {
+                data.as_ptr().add(i).write(value.clone());
+            }
+            Grid { data, layout, lod }
+        }
+    }
+}
+
+impl<T: Default + Clone> Grid<T> {
+    pub fn new<M>(chunk: &Chunk<T, M>, lod: u8) -> Grid<T> {
+        Self::new_aligned(chunk, lod, std::mem::align_of::<T>())
+    }
+
+    /// Same as `new`, but the cells are allocated with at least the given alignment.
+    pub fn new_aligned<M>(chunk: &Chunk<T, M>, lod: u8, align: usize) -> Grid<T> {
+        let mut grid = Self::allocate(lod, align, Default::default());
         grid.build_chunk_recursive(&chunk.root, lod, (0, 0, 0));
         grid
     }
 
+    /// Same as `new`, but fails when `lod` is shallower than the deepest leaf of the chunk.
+    /// Building the grid with `chunk_depth` instead keeps all the detail.
+    pub fn new_checked<M>(chunk: &Chunk<T, M>, lod: u8) -> Result<Grid<T>, LodTooCoarse> {
+        let chunk_depth = chunk.max_depth();
+        if lod < chunk_depth {
+            Err(LodTooCoarse { lod, chunk_depth })
+        } else {
+            Ok(Self::new(chunk, lod))
+        }
+    }
+
     fn build_chunk_recursive(&mut self, node: &Node<T>, lod: u8, location: (usize, usize, usize)) {
-        if lod == 1 { // base case
-            // Copy data into the grid
-            for (dir, data) in node.data.enumerate() {
-                let offset = dir.breakdown();
-                let coords = (location.0 + offset.0 as usize, location.1 + offset.1 as usize, location.2 + offset.2 as usize);
-                self[coords] = data.clone();
-            }
-            return;
+        let mut set = |coords: (usize, usize, usize), value: &T| self[coords] = value.clone();
+        for dir in (0..8_u8).map(Direction::from) {
+            Self::build_octant(node, dir, lod, location, &mut set);
         }
-        // Inductive steps.
-        let new_lod = lod - 1;
-        let size: usize = 1 << new_lod;
-        for (dir, child) in node.children.enumerate() {
+    }
 
-            let mut newlocation = location;
-            if dir.is_max_x() {
-                newlocation.0 += size;
-            }
-            if dir.is_max_y() {
-                newlocation.1 += size;
-            }
-            if dir.is_max_z() {
-                newlocation.2 += size;
+    /// Write the cells covered by the octant `dir` of a node spanning `1 << lod` cells from `location`.
+    fn build_octant<F>(node: &Node<T>, dir: Direction, lod: u8, location: (usize, usize, usize), set: &mut F)
+        where F: FnMut((usize, usize, usize), &T) {
+        let size: usize = 1 << (lod - 1);
+        let offset = dir.breakdown();
+        let location = (
+            location.0 + offset.0 as usize * size,
+            location.1 + offset.1 as usize * size,
+            location.2 + offset.2 as usize * size,
+        );
+        match &node.children[dir] {
+            Some(child) if lod > 1 => {
+                for child_dir in (0..8_u8).map(Direction::from) {
+                    Self::build_octant(child, child_dir, lod - 1, location, set);
+                }
             }
-
-            if let Some(child) = child {
-                self.build_chunk_recursive(child, new_lod, newlocation);
-            } else {
+            _ => {
                 // Fill area
-                let fakedata = &node.data[dir];
-
-                for i in 0 .. size {
-                    for j in 0 .. size {
-                        for k in 0 .. size {
-                            let mut newlocation = newlocation;
-                            newlocation.0 += i;
-                            newlocation.1 += j;
-                            newlocation.2 += k;
-                            self[newlocation] = fakedata.clone();
+                let data = &node.data[dir];
+                for i in 0..size {
+                    for j in 0..size {
+                        for k in 0..size {
+                            set((location.0 + i, location.1 + j, location.2 + k), data);
                         }
                     }
                 }
             }
         }
     }
+
+}
+
+
+// Cells of a grid shared between the threads of `new_par`, each writing to its own octant
+#[cfg(feature = "rayon")]
+struct SharedCells<T>(NonNull<T>);
+#[cfg(feature = "rayon")]
+unsafe impl<T: Send> Sync for SharedCells<T> {}
+
+#[cfg(feature = "rayon")]
+impl<T: Default + Clone + Send + Sync> Grid<T> {
+    /// Same as `new`, but the eight octants of the root are built on separate threads.
+    pub fn new_par<M>(chunk: &Chunk<T, M>, lod: u8) -> Grid<T> {
+        use rayon::prelude::*;
+
+        let grid = Self::allocate(lod, std::mem::align_of::<T>(), Default::default());
+        let cells = SharedCells(grid.data);
+        let root = &chunk.root;
+        (0..8_u8).into_par_iter().for_each(|dir| {
+            let cells = &cells;
+            let mut set = |coords: (usize, usize, usize), value: &T| unsafe {
+                // The octants cover disjoint regions of the grid, so no cell is written twice
+                *cells.0.as_ptr().add(offset(lod, coords)) = value.clone();
+            };
+            Self::build_octant(root, dir.into(), lod, (0, 0, 0), &mut set);
+        });
+        grid
+    }
 }
 
+impl<T: Clone> Grid<T> {
+    /// A grid of `2^lod` cells per side, all holding `value`.
+    pub fn filled(lod: u8, value: T) -> Grid<T> {
+        Self::allocate(lod, std::mem::align_of::<T>(), value)
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.data.as_ptr(), 1 << (self.lod * 3)) }
+    }
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.data.as_ptr(), 1 << (self.lod * 3)) }
+    }
+    pub fn get_lod(&self) -> u8 {
+        self.lod
+    }
+    /// Number of cells along each side.
+    pub fn get_size(&self) -> usize {
+        1 << self.lod
+    }
+}
 
 impl<T> Index<(usize, usize, usize)> for Grid<T> {
     type Output = T;
 
     fn index(&self, index: (usize, usize, usize)) -> &Self::Output {
-        debug_assert!(index.0 < (1 << self.lod));
-        debug_assert!(index.1 < (1 << self.lod));
-        debug_assert!(index.2 < (1 << self.lod));
-        &self.data[(index.2 | (index.1 << self.lod) | (index.0 << (2 * self.lod))) as usize]
+        &self.as_slice()[offset(self.lod, index)]
     }
 }
 impl<T> IndexMut<(usize, usize, usize)> for Grid<T> {
     fn index_mut(&mut self, index: (usize, usize, usize)) -> &mut Self::Output {
-        &mut self.data[(index.2 | (index.1 << self.lod) | (index.0 << (2 * self.lod))) as usize]
+        let lod = self.lod;
+        &mut self.as_mut_slice()[offset(lod, index)]
+    }
+}
+
+/// A grid with the lod carried in its type, so that grids of different lods can't be mixed up.
+///
+/// ```compile_fail
+/// use octree::chunk::Chunk;
+/// use octree::grid::FixedGrid;
+///
+/// fn mesh(_grid: &FixedGrid<u16, 3>) {}
+///
+/// let chunk: Chunk<u16> = Chunk::new();
+/// mesh(&FixedGrid::<u16, 4>::new(&chunk));
+/// ```
+pub struct FixedGrid<T, const LOD: u8> {
+    grid: Grid<T>,
+}
+
+impl<T: Default + Clone, const LOD: u8> FixedGrid<T, LOD> {
+    pub fn new<M>(chunk: &Chunk<T, M>) -> Self {
+        FixedGrid {
+            grid: Grid::new(chunk, LOD),
+        }
+    }
+}
+
+impl<T, const LOD: u8> FixedGrid<T, LOD> {
+    /// Number of cells on each side.
+    pub const SIZE: usize = 1 << LOD;
+
+    pub fn get(&self, index: (usize, usize, usize)) -> Option<&T> {
+        if index.0 < Self::SIZE && index.1 < Self::SIZE && index.2 < Self::SIZE {
+            Some(&self.grid[index])
+        } else {
+            None
+        }
+    }
+    pub fn as_grid(&self) -> &Grid<T> {
+        &self.grid
+    }
+    pub fn into_grid(self) -> Grid<T> {
+        self.grid
+    }
+}
+
+impl<T, const LOD: u8> Index<(usize, usize, usize)> for FixedGrid<T, LOD> {
+    type Output = T;
+
+    fn index(&self, index: (usize, usize, usize)) -> &Self::Output {
+        self.get(index).expect("Grid index out of range")
+    }
+}
+impl<T, const LOD: u8> IndexMut<(usize, usize, usize)> for FixedGrid<T, LOD> {
+    fn index_mut(&mut self, index: (usize, usize, usize)) -> &mut Self::Output {
+        assert!(index.0 < Self::SIZE && index.1 < Self::SIZE && index.2 < Self::SIZE, "Grid index out of range");
+        &mut self.grid[index]
+    }
+}
+
+impl<T> Drop for Grid<T> {
+    fn drop(&mut self) {
+        unsafe {
+            std::ptr::drop_in_place(self.as_mut_slice());
+            if self.layout.size() != 0 {
+                dealloc(self.data.as_ptr() as *mut u8, self.layout);
+            }
+        }
     }
 }
 
@@ -101,7 +274,7 @@ impl<'a, T> Iterator for GridIterator<'a, T> {
         if self.location >= capacity {
             None
         } else {
-            let item = &self.grid.data[self.location];
+            let item = &self.grid.as_slice()[self.location];
 
             let mask = (1 << lod) - 1;
             let z = self.location & mask;
@@ -152,6 +325,60 @@ impl<'a, T> Iterator for GridGroupedIterator<'a, T> {
     }
 }
 
+/// A cell along with its neighborhood. Neighbors outside the grid are `None`.
+pub struct Stencil<'a, T> {
+    grid: &'a Grid<T>,
+    position: (usize, usize, usize),
+}
+
+impl<'a, T> Stencil<'a, T> {
+    pub fn center(&self) -> &'a T {
+        &self.grid[self.position]
+    }
+    /// The cell at an offset from the center, covering both face and diagonal neighbors.
+    pub fn at(&self, offset: (i32, i32, i32)) -> Option<&'a T> {
+        let size = self.grid.get_size() as i64;
+        let x = self.position.0 as i64 + offset.0 as i64;
+        let y = self.position.1 as i64 + offset.1 as i64;
+        let z = self.position.2 as i64 + offset.2 as i64;
+        if x < 0 || y < 0 || z < 0 || x >= size || y >= size || z >= size {
+            None
+        } else {
+            Some(&self.grid[(x as usize, y as usize, z as usize)])
+        }
+    }
+    pub fn neighbor(&self, face: Face) -> Option<&'a T> {
+        self.at(face.offset())
+    }
+}
+
+pub struct GridStencilIterator<'a, T> {
+    grid: &'a Grid<T>,
+    location: usize,
+}
+
+impl<'a, T> Iterator for GridStencilIterator<'a, T> {
+    type Item = ((usize, usize, usize), Stencil<'a, T>);
+    fn next(&mut self) -> Option<Self::Item> {
+        let lod = self.grid.lod;
+        let capacity = 1 << (lod * 3);
+        if self.location >= capacity {
+            None
+        } else {
+            let mask = (1 << lod) - 1;
+            let z = self.location & mask;
+            let y = (self.location >> lod) & mask;
+            let x = self.location >> (lod * 2);
+            self.location += 1;
+
+            Some(((x, y, z), Stencil {
+                grid: self.grid,
+                position: (x, y, z),
+            }))
+        }
+    }
+}
+
 impl<'a, T> Grid<T> {
     pub fn iter(&'a self) -> GridIterator<'a, T> {
         GridIterator {
@@ -165,13 +392,168 @@ impl<'a, T> Grid<T> {
             location: 0,
         }
     }
+    pub fn iter_stencil(&'a self) -> GridStencilIterator<'a, T> {
+        GridStencilIterator {
+            grid: self,
+            location: 0,
+        }
+    }
+}
+
+impl<T: VoxelData> Grid<T> {
+    /// Number of cells in `iter_grouped` with both empty and solid corners.
+    /// These are the cells producing triangles in the marching cubes mesher.
+    pub fn surface_cell_count(&self) -> usize {
+        self.iter_grouped()
+            .filter(|(_, cell)| {
+                let empty = cell.iter().filter(|value| value.is_empty()).count();
+                empty != 0 && empty != 8
+            })
+            .count()
+    }
+    /// Whether the cell and its six face neighbors are all solid, so that it can't be on the surface.
+    /// Cells on the border of the grid are never interior, as their outer neighbors are unknown.
+    pub fn is_interior(&self, position: (usize, usize, usize)) -> bool {
+        let stencil = Stencil { grid: self, position };
+        !stencil.center().is_empty() && Face::ALL.iter()
+            .all(|face| stencil.neighbor(*face).is_some_and(|neighbor| !neighbor.is_empty()))
+    }
+    /// Empty cells with at least one solid face neighbor, e.g. to seed a fluid simulation.
+    pub fn surface_air_cells(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.iter()
+            .filter(|(_, value)| value.is_empty())
+            .map(|(position, _)| position)
+            .filter(move |position| {
+                let stencil = Stencil { grid: self, position: *position };
+                Face::ALL.iter()
+                    .any(|face| stencil.neighbor(*face).is_some_and(|neighbor| !neighbor.is_empty()))
+            })
+    }
+}
+
+impl<T: Clone + PartialEq> Grid<T> {
+    fn build_node_recursive(&self, lod: u8, location: (usize, usize, usize)) -> Node<T> {
+        let size: usize = 1 << (lod - 1);
+        let mut node = Node {
+            children: Box::new(Direction::map(|_| None)),
+            data: Direction::map(|dir| {
+                let offset = dir.breakdown();
+                self[(
+                    location.0 + offset.0 as usize * size,
+                    location.1 + offset.1 as usize * size,
+                    location.2 + offset.2 as usize * size,
+                )].clone()
+            }),
+        };
+        if lod == 1 {
+            return node;
+        }
+        for (dir, child) in node.children.enumerate_mut() {
+            let offset = dir.breakdown();
+            let child_location = (
+                location.0 + offset.0 as usize * size,
+                location.1 + offset.1 as usize * size,
+                location.2 + offset.2 as usize * size,
+            );
+            let subnode = self.build_node_recursive(lod - 1, child_location);
+            if subnode.children.iter().any(|c| c.is_some()) || subnode.data.data.windows(2).any(|w| w[0] != w[1]) {
+                *child = Some(subnode);
+            }
+        }
+        node
+    }
+}
+
+impl<T: Clone + PartialEq> Chunk<T> {
+    /// Build a chunk from the cells of a grid, merging uniform regions.
+    pub fn from_grid(grid: &Grid<T>) -> Chunk<T> {
+        Chunk {
+            root: grid.build_node_recursive(grid.lod, (0, 0, 0)),
+            meta: HashMap::new(),
+            mesh_style: MeshStyle::default(),
+        }
+    }
+}
+
+impl<T: Default + Clone + Lerp> Grid<T> {
+    /// Same as `new`, but when `lod` is deeper than `Chunk::max_depth`, the chunk is sampled
+    /// on the grid of that depth and the finer cells are trilinearly interpolated between the
+    /// centers of its cells instead of copied. Coarser leaves span several of those cells,
+    /// so they stay flat away from their boundaries.
+    pub fn new_interpolated<M>(chunk: &Chunk<T, M>, lod: u8) -> Grid<T> {
+        let chunk_depth = chunk.max_depth();
+        if lod <= chunk_depth {
+            return Self::new(chunk, lod);
+        }
+        let coarse = Self::new(chunk, chunk_depth);
+        let max = coarse.get_size() - 1;
+        let scale = coarse.get_size() as f32 / (1 << lod) as f32;
+        // Position of a fine cell center in the coarse grid, where the coarse cell centers are on integers
+        let sample = |i: usize| -> (usize, usize, f32) {
+            let position = ((i as f32 + 0.5) * scale - 0.5).clamp(0.0, max as f32);
+            let lower = position.floor() as usize;
+            (lower, (lower + 1).min(max), position - lower as f32)
+        };
+
+        let mut grid = Self::allocate(lod, std::mem::align_of::<T>(), Default::default());
+        let size = grid.get_size();
+        for x in 0..size {
+            let (x0, x1, tx) = sample(x);
+            for y in 0..size {
+                let (y0, y1, ty) = sample(y);
+                for z in 0..size {
+                    let (z0, z1, tz) = sample(z);
+                    let lerp_z = |x: usize, y: usize| coarse[(x, y, z0)].lerp(&coarse[(x, y, z1)], tz);
+                    let lerp_yz = |x: usize| lerp_z(x, y0).lerp(&lerp_z(x, y1), ty);
+                    grid[(x, y, z)] = lerp_yz(x0).lerp(&lerp_yz(x1), tx);
+                }
+            }
+        }
+        grid
+    }
+}
+
+/// Keeps recycled grids so that grids of the same lod can reuse their allocation.
+pub struct GridPool<T> {
+    free: HashMap<u8, Vec<Grid<T>>>,
+}
+
+impl<T> Default for GridPool<T> {
+    fn default() -> Self {
+        GridPool {
+            free: HashMap::new(),
+        }
+    }
+}
+
+impl<T> GridPool<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn recycle(&mut self, grid: Grid<T>) {
+        self.free.entry(grid.lod).or_default().push(grid);
+    }
+}
+
+impl<T: Default + Clone> GridPool<T> {
+    /// Same as `Grid::new`, but reuses a recycled grid when one is available.
+    pub fn get<M>(&mut self, chunk: &Chunk<T, M>, lod: u8) -> Grid<T> {
+        match self.free.get_mut(&lod).and_then(|grids| grids.pop()) {
+            Some(mut grid) => {
+                // Every cell gets overwritten
+                grid.build_chunk_recursive(&chunk.root, lod, (0, 0, 0));
+                grid
+            }
+            None => Grid::new(chunk, lod),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::chunk::Chunk;
     use crate::index_path::IndexPath;
-    use super::Grid;
+    use super::{Grid, FixedGrid, LodTooCoarse, MAX_LOD, offset};
     use crate::direction::Direction;
 
     #[test]
@@ -186,12 +568,12 @@ mod tests {
 
         for (location, expected_value) in &[
             ((0, 0, 0), 0),
-            ((0, 0, 1), 1),
+            ((0, 0, 1), 4),
             ((0, 1, 0), 2),
-            ((0, 1, 1), 3),
-            ((1, 0, 0), 4),
+            ((0, 1, 1), 6),
+            ((1, 0, 0), 1),
             ((1, 0, 1), 5),
-            ((1, 1, 0), 6),
+            ((1, 1, 0), 3),
             ((1, 1, 1), 7)
         ] {
             let (coords, value) = iter.next().unwrap();
@@ -218,14 +600,225 @@ mod tests {
         0, 0, 4, 4, 0, 0, 4, 4,
         2, 2, 6, 6, 2, 2, 6, 6,
         1, 1, 5, 5, 1, 1, 5, 5,
-        3, 3, 16, 17, 3, 3, 18, 19,
+        3, 3, 16, 20, 3, 3, 18, 22,
         1, 1, 5, 5, 1, 1, 5, 5,
-        3, 3, 20, 21, 3, 3, 22, 23] {
+        3, 3, 17, 21, 3, 3, 19, 23] {
             assert_eq!(*iter.next().unwrap().1, *i);
         }
         assert!(iter.next().is_none());
     }
 
+    #[test]
+    fn test_new_aligned() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 2, 3), 3), 1);
+        for &align in &[16, 256, 4096] {
+            let grid = Grid::new_aligned(&chunk, 3, align);
+            assert_eq!(grid.as_slice().as_ptr() as usize % align, 0);
+            assert_eq!(grid[(1, 2, 3)], 1);
+            assert_eq!(grid.iter().filter(|(_, value)| **value == 1).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_new_interpolated() {
+        let mut chunk: Chunk<f32> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 0, 0), 1), 1.0);
+
+        let replicated = Grid::new(&chunk, 3);
+        let grid = Grid::new_interpolated(&chunk, 3);
+        assert_eq!(grid.get_size(), 8);
+
+        // Along x the values rise smoothly from the empty octant to the filled one
+        let row: Vec<f32> = (0..8).map(|x| grid[(x, 0, 0)]).collect();
+        assert!(row.windows(2).all(|w| w[0] <= w[1] && w[1] - w[0] < 0.5));
+        assert_eq!(row[0], 0.0);
+        assert_eq!(row[7], 1.0);
+        assert!(row.iter().any(|value| *value > 0.0 && *value < 1.0));
+        let replicated_row: Vec<f32> = (0..8).map(|x| replicated[(x, 0, 0)]).collect();
+        assert_eq!(replicated_row, vec![0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0]);
+
+        // No upscaling at or below the chunk depth
+        let grid = Grid::new_interpolated(&chunk, 1);
+        assert_eq!(grid[(1, 0, 0)], 1.0);
+        assert_eq!(grid[(0, 0, 0)], 0.0);
+
+        // With leaves of mixed depths, the values are interpolated over cells of the deepest one
+        let mut chunk: Chunk<f32> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 0, 0), 1), 1.0);
+        chunk.set(IndexPath::from_cell((0, 0, 0), 2), 1.0);
+        let grid = Grid::new_interpolated(&chunk, 4);
+        let row: Vec<f32> = (0..16).map(|x| grid[(x, 0, 0)]).collect();
+        assert_eq!(&row[..10], &[1.0, 1.0, 0.875, 0.625, 0.375, 0.125, 0.125, 0.375, 0.625, 0.875]);
+        // The coarse leaf is flat away from its boundary
+        assert!(row[10..].iter().all(|value| *value == 1.0));
+    }
+
+    #[test]
+    fn test_max_lod() {
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(MAX_LOD, 21);
+        #[cfg(target_pointer_width = "32")]
+        assert_eq!(MAX_LOD, 10);
+
+        // The last cell of the largest grid still fits
+        let last = (1 << MAX_LOD) - 1;
+        assert_eq!(offset(MAX_LOD, (last, last, last)), (1 << (3 * MAX_LOD as usize)) - 1);
+        assert_eq!(offset(MAX_LOD, (1, 0, 0)), 1 << (2 * MAX_LOD as usize));
+        assert_eq!(offset(MAX_LOD, (0, 1, 0)), 1 << MAX_LOD);
+        assert_eq!(offset(MAX_LOD, (0, 0, 1)), 1);
+
+        let result = std::panic::catch_unwind(|| Grid::filled(MAX_LOD + 1, 0_u8));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_fixed_grid() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((5, 2, 7), 3), 4);
+
+        let mut grid: FixedGrid<u16, 3> = FixedGrid::new(&chunk);
+        assert_eq!(FixedGrid::<u16, 3>::SIZE, 8);
+        assert_eq!(grid[(5, 2, 7)], 4);
+        assert_eq!(grid.get((5, 2, 8)), None);
+        grid[(0, 0, 0)] = 1;
+        assert_eq!(grid.as_grid()[(0, 0, 0)], 1);
+        assert_eq!(grid.into_grid().get_lod(), 3);
+    }
+
+    #[test]
+    fn test_surface_cell_count() {
+        use crate::density::DensityVoxel;
+
+        // Solid below half height
+        let mut chunk: Chunk<DensityVoxel> = Chunk::new();
+        for dir in (0..4_u8).map(Direction::from) {
+            chunk.set(IndexPath::new().push(dir), DensityVoxel::new(1, 0));
+        }
+        let grid = Grid::new(&chunk, 2);
+        // The cells between the second and third layers
+        assert_eq!(grid.surface_cell_count(), 3 * 3);
+        let grid = Grid::new(&chunk, 3);
+        assert_eq!(grid.surface_cell_count(), 7 * 7);
+
+        let empty: Chunk<DensityVoxel> = Chunk::new();
+        assert_eq!(Grid::new(&empty, 3).surface_cell_count(), 0);
+    }
+
+    #[test]
+    fn test_is_interior() {
+        use crate::density::DensityVoxel;
+
+        let mut grid = Grid::filled(3, DensityVoxel::default());
+        for x in 2..5 {
+            for y in 2..5 {
+                for z in 2..5 {
+                    grid[(x, y, z)] = DensityVoxel::new(1, 0);
+                }
+            }
+        }
+        let interior: Vec<_> = grid.iter()
+            .map(|(position, _)| position)
+            .filter(|position| grid.is_interior(*position))
+            .collect();
+        assert_eq!(interior, vec![(3, 3, 3)]);
+
+        // Neighbors outside of the grid don't count as solid
+        let solid = Grid::filled(2, DensityVoxel::new(1, 0));
+        assert!(solid.is_interior((1, 2, 1)));
+        assert!(!solid.is_interior((0, 2, 1)));
+        assert!(!solid.is_interior((1, 3, 1)));
+    }
+
+    #[test]
+    fn test_surface_air_cells() {
+        use crate::density::DensityVoxel;
+
+        let mut grid = Grid::filled(3, DensityVoxel::default());
+        for x in 2..4 {
+            for y in 2..4 {
+                for z in 2..4 {
+                    grid[(x, y, z)] = DensityVoxel::new(1, 0);
+                }
+            }
+        }
+        let cells: Vec<_> = grid.surface_air_cells().collect();
+        // Four cells against each of the six sides of the block
+        assert_eq!(cells.len(), 6 * 4);
+        assert!(cells.contains(&(1, 2, 3)));
+        assert!(cells.contains(&(2, 3, 4)));
+        // Diagonal neighbors, far away air and the block itself
+        assert!(!cells.contains(&(1, 1, 2)));
+        assert!(!cells.contains(&(7, 7, 7)));
+        assert!(!cells.contains(&(2, 2, 2)));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_new_par() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 2, 3), 3), 1);
+        chunk.set(IndexPath::from_cell((7, 0, 5), 3), 2);
+        chunk.set(IndexPath::from_cell((1, 1, 0), 1), 3);
+        chunk.set(IndexPath::from_cell((20, 9, 30), 5), 4);
+        for lod in 1..6 {
+            let grid = Grid::new(&chunk, lod);
+            let par = Grid::new_par(&chunk, lod);
+            assert_eq!(par.as_slice(), grid.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_new_checked() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 2, 3), 3), 1);
+        assert_eq!(chunk.max_depth(), 3);
+        assert_eq!(Grid::new_checked(&chunk, 2).err(), Some(LodTooCoarse { lod: 2, chunk_depth: 3 }));
+
+        let grid = Grid::new_checked(&chunk, 3).unwrap();
+        assert_eq!(grid[(1, 2, 3)], 1);
+        assert!(Grid::new_checked(&chunk, 4).is_ok());
+    }
+
+    #[test]
+    fn test_from_grid() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 2, 3), 3), 1);
+        chunk.set(IndexPath::new().push(Direction::RearRightTop), 2);
+        let grid = Grid::new(&chunk, 3);
+        let rebuilt = Chunk::from_grid(&grid);
+        assert_eq!(rebuilt.max_depth(), 3);
+        for ((x, y, z), value) in grid.iter() {
+            assert_eq!(rebuilt.get(IndexPath::from_cell((x as u32, y as u32, z as u32), 3)), value);
+        }
+        assert!(rebuilt.root.children[Direction::RearRightTop].is_none());
+    }
+
+    #[test]
+    fn test_stencil_iterator() {
+        use crate::direction::Face;
+
+        let mut chunk: Chunk<u16> = Chunk::new();
+        for dir in &[Direction::FrontLeftBottom, Direction::RearLeftBottom, Direction::FrontLeftTop, Direction::RearLeftTop] {
+            chunk.set(IndexPath::new().push(*dir), 1);
+        }
+        let grid = Grid::new(&chunk, 2);
+
+        let mut count = 0;
+        for ((x, y, z), stencil) in grid.iter_stencil() {
+            assert_eq!(*stencil.center(), grid[(x, y, z)]);
+            assert_eq!(stencil.neighbor(Face::Left).is_none(), x == 0);
+            assert_eq!(stencil.at((1, 1, 1)).is_none(), x == 3 || y == 3 || z == 3);
+
+            let is_edge = Face::ALL.iter()
+                .filter_map(|face| stencil.neighbor(*face))
+                .any(|neighbor| neighbor != stencil.center());
+            assert_eq!(is_edge, x == 1 || x == 2);
+            count += 1;
+        }
+        assert_eq!(count, 64);
+    }
+
     #[test]
     fn test_grouped_iterator() {
         let mut chunk: Chunk<u16> = Chunk::new();
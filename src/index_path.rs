@@ -1,12 +1,14 @@
 use std::fmt::Write;
 use std::num::NonZeroU64;
-use super::direction::Direction;
+use std::str::FromStr;
+use super::direction::{Direction, Face};
+use glam as math;
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub struct IndexPath(NonZeroU64);
 
 impl IndexPath {
-    const MAX_SIZE: u8 = 21;
+    pub(crate) const MAX_SIZE: u8 = 21;
 
     pub fn new() -> Self {
         unsafe {
@@ -33,23 +35,35 @@ impl IndexPath {
         }
     }
     pub fn push(&self, octant: Direction) -> Self {
-        assert!(!self.is_full(), "The index path is full");
+        self.checked_push(octant).expect("The index path is full")
+    }
+    /// Same as `push`, but returns None when the path is full.
+    pub fn checked_push(&self, octant: Direction) -> Option<Self> {
+        if self.is_full() {
+            return None;
+        }
         unsafe {
             let num = (Into::<u64>::into(*self) << 3) | (octant as u64);
-            Self::from(NonZeroU64::new_unchecked(num))
+            Some(Self::from(NonZeroU64::new_unchecked(num)))
         }
     }
     pub fn count(&self) -> u8 {
         Self::MAX_SIZE - (Into::<u64>::into(*self).leading_zeros() / 3) as u8
     }
     pub fn put(&self, octant: Direction) -> Self {
-        assert!(!self.is_full(), "The index path is full");
+        self.checked_put(octant).expect("The index path is full")
+    }
+    /// Same as `put`, but returns None when the path is full.
+    pub fn checked_put(&self, octant: Direction) -> Option<Self> {
+        if self.is_full() {
+            return None;
+        }
         let mut val = Into::<u64>::into(*self);
         let num_bits = 64 - val.leading_zeros() - 1;
         val &= !(0b111 << num_bits); // clear those bits
         val |= (octant as u64 | 0b1000) << num_bits; // Set back those bits
         unsafe {
-            Self::from(NonZeroU64::new_unchecked(val))
+            Some(Self::from(NonZeroU64::new_unchecked(val)))
         }
     }
     pub fn get(&self) -> Direction {
@@ -74,10 +88,146 @@ impl IndexPath {
             Self::from(NonZeroU64::new_unchecked((Into::<u64>::into(*self) & !0b111) | (octant as u64)))
         }
     }
+    /// The octants are packed into the integer with a leading 1 bit marking the end of the path.
+    /// `push`/`peek`/`pop` operate on the low end of the path, while `put`/`get`/`del`
+    /// operate on the high end. Returns the path with the octants in reverse order, so that
+    /// `get`/`del` on the result yield the same octants as `peek`/`pop` on the original,
+    /// and vice versa.
+    pub fn reversed(&self) -> Self {
+        let mut reversed = Self::new();
+        for dir in *self {
+            reversed = reversed.push(dir);
+        }
+        reversed
+    }
+    /// Index path of the cell at `coords` on a grid of `2^depth` cells per side.
+    /// The octant at each level is picked with the same axis convention as `Bounds`.
+    pub fn from_cell(coords: (u32, u32, u32), depth: u8) -> Self {
+        assert!(depth <= Self::MAX_SIZE, "The index path is full");
+        let mut index_path = Self::new();
+        for level in (0..depth).rev() {
+            let x = (coords.0 >> level) & 1;
+            let y = (coords.1 >> level) & 1;
+            let z = (coords.2 >> level) & 1;
+            index_path = index_path.put(Direction::from_coords(x as u8, y as u8, z as u8));
+        }
+        index_path
+    }
+    /// The ancestor at `depth`, keeping the first `depth` octants from the root.
+    /// Paths no deeper than `depth` are returned unchanged.
+    pub fn truncate(&self, depth: u8) -> Self {
+        if depth >= self.len() {
+            return *self;
+        }
+        let num_bits = depth as u32 * 3;
+        let val = (self.0.get() & ((1 << num_bits) - 1)) | (1 << num_bits);
+        unsafe {
+            Self::from(NonZeroU64::new_unchecked(val))
+        }
+    }
+    /// Append the octants of `other`, a path relative to this one, below the deepest octant.
+    pub fn concat(&self, other: &IndexPath) -> Self {
+        let len = self.len();
+        assert!(len + other.len() <= Self::MAX_SIZE, "The index path is full");
+        let num_bits = len as u32 * 3;
+        let val = (self.0.get() & ((1 << num_bits) - 1)) | (other.0.get() << num_bits);
+        unsafe {
+            Self::from(NonZeroU64::new_unchecked(val))
+        }
+    }
+    /// The longest path from the root shared by both paths.
+    pub fn common_prefix(&self, other: IndexPath) -> Self {
+        let mut prefix = Self::new();
+        for (a, b) in self.zip(other) {
+            if a != b {
+                break;
+            }
+            prefix = prefix.put(a);
+        }
+        prefix
+    }
+    /// The deepest node containing both paths, or the root when they diverge right away.
+    pub fn common_ancestor(&self, other: &IndexPath) -> Self {
+        self.common_prefix(*other)
+    }
+    /// Whether `other` lies strictly below this path.
+    pub fn is_ancestor_of(&self, other: &IndexPath) -> bool {
+        self.len() < other.len() && other.common_ancestor(self) == *self
+    }
+    /// Whether this path lies strictly below `other`.
+    pub fn is_descendant_of(&self, other: &IndexPath) -> bool {
+        other.is_ancestor_of(self)
+    }
+    /// Coordinates of the cell on a grid of `2^len` cells per side. Inverse of `from_cell`.
+    pub fn to_cell(&self) -> (u32, u32, u32) {
+        let mut cell = (0, 0, 0);
+        for dir in *self {
+            let (x, y, z) = dir.breakdown();
+            cell = (cell.0 << 1 | x as u32, cell.1 << 1 | y as u32, cell.2 << 1 | z as u32);
+        }
+        cell
+    }
+    /// Morton code of the voxel along with its depth. Each octant from the root contributes
+    /// three bits, x in the lowest, so the first octant ends up in the highest bits.
+    pub fn to_morton(&self) -> (u64, u8) {
+        let mut code = 0;
+        for dir in *self {
+            let (x, y, z) = dir.breakdown();
+            code = code << 3 | (x | y << 1 | z << 2) as u64;
+        }
+        (code, self.len())
+    }
+    /// Path of the voxel with the Morton code at `depth`. Inverse of `to_morton`.
+    pub fn from_morton(code: u64, depth: u8) -> Self {
+        assert!(depth <= Self::MAX_SIZE, "Depth {} exceeds the maximum index path length of {}", depth, Self::MAX_SIZE);
+        let mut index_path = Self::new();
+        for level in (0..depth as u32).rev() {
+            let octant = (code >> (3 * level)) as u8 & 0b111;
+            index_path = index_path.put(octant.into());
+        }
+        index_path
+    }
+    /// Path of the voxel at the same depth sharing the face, or None when it lies outside of the root.
+    /// Stepping across the border of a node carries over to its ancestors, so the neighbor
+    /// may be in a different branch of the tree.
+    pub fn neighbor(&self, face: Face) -> Option<Self> {
+        let depth = self.len();
+        let size = 1_i64 << depth;
+        let cell = self.to_cell();
+        let offset = face.offset();
+        let neighbor = (cell.0 as i64 + offset.0 as i64, cell.1 as i64 + offset.1 as i64, cell.2 as i64 + offset.2 as i64);
+        let inside = |v: i64| v >= 0 && v < size;
+        if inside(neighbor.0) && inside(neighbor.1) && inside(neighbor.2) {
+            Some(Self::from_cell((neighbor.0 as u32, neighbor.1 as u32, neighbor.2 as u32), depth))
+        } else {
+            None
+        }
+    }
+    /// Index path of the cell containing the point on a grid of `2^depth` cells per side,
+    /// with the point in chunk units. Returns None for points outside of the chunk.
+    pub fn locate(point: math::Vec3A, depth: u8) -> Option<Self> {
+        let size = (1_u64 << depth) as f32;
+        let cell = |v: f32| if (0.0..1.0).contains(&v) {
+            // Rounding may put points right below 1 onto the next cell
+            Some(((v * size) as u32).min(size as u32 - 1))
+        } else {
+            None
+        };
+        Some(Self::from_cell((cell(point.x())?, cell(point.y())?, cell(point.z())?), depth))
+    }
     pub fn len(&self) -> u8 {
         let num_empty_slots = Into::<u64>::into(*self).leading_zeros() as u8 / 3;
         Self::MAX_SIZE - num_empty_slots
     }
+    /// Index path from its packed representation, or None if the value isn't a valid path:
+    /// the leading 1 bit has to sit right above a whole number of octants.
+    pub fn from_raw(val: u64) -> Option<Self> {
+        let val = NonZeroU64::new(val)?;
+        if (63 - val.get().leading_zeros()) % 3 != 0 {
+            return None;
+        }
+        Some(Self::from(val))
+    }
 }
 
 impl From<NonZeroU64> for IndexPath {
@@ -90,6 +240,29 @@ impl From<IndexPath> for u64 {
     fn from(index_path: IndexPath) -> u64 { index_path.0.get() }
 }
 
+/// Paths are ordered the way `iter_leaf` visits them: octant by octant from the root,
+/// with a path sorting right before its descendants.
+impl Ord for IndexPath {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let mut a = *self;
+        let mut b = *other;
+        loop {
+            match (a.next(), b.next()) {
+                (Some(x), Some(y)) if x == y => continue,
+                (Some(x), Some(y)) => return (x as u8).cmp(&(y as u8)),
+                (None, Some(_)) => return std::cmp::Ordering::Less,
+                (Some(_), None) => return std::cmp::Ordering::Greater,
+                (None, None) => return std::cmp::Ordering::Equal,
+            }
+        }
+    }
+}
+impl PartialOrd for IndexPath {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(Ord::cmp(self, other))
+    }
+}
+
 impl Iterator for IndexPath {
     type Item = Direction;
 
@@ -104,6 +277,65 @@ impl Iterator for IndexPath {
     }
 }
 
+/// Walks the path from the deepest octant back towards the root.
+impl DoubleEndedIterator for IndexPath {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.is_empty() {
+            None
+        } else {
+            let dir = self.get();
+            self.0 = self.del().0;
+            Some(dir)
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum ParseIndexPathError {
+    /// A segment isn't a single octant digit between 0 and 7.
+    InvalidOctant(String),
+    /// The path has more octants than an index path can hold.
+    TooLong,
+}
+
+impl std::fmt::Display for ParseIndexPathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseIndexPathError::InvalidOctant(segment) => write!(f, "invalid octant {:?}, expected a digit between 0 and 7", segment),
+            ParseIndexPathError::TooLong => write!(f, "index path longer than {} octants", IndexPath::MAX_SIZE),
+        }
+    }
+}
+
+impl std::error::Error for ParseIndexPathError {}
+
+/// Parses the `Debug` format `(Root)/0/3/7`, or the bare octants `0/3/7`, listed from the root.
+/// An empty string or `(Root)` alone is the root.
+impl FromStr for IndexPath {
+    type Err = ParseIndexPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = match s.strip_prefix("(Root)") {
+            Some(path) if path.is_empty() || path.starts_with('/') => path,
+            Some(_) => return Err(ParseIndexPathError::InvalidOctant(s.to_string())),
+            None => s,
+        };
+        let path = path.strip_prefix('/').unwrap_or(path);
+        let mut index_path = IndexPath::new();
+        if path.is_empty() {
+            return Ok(index_path);
+        }
+        for segment in path.split('/') {
+            let octant = match segment.as_bytes() {
+                [digit @ b'0'..=b'7'] => digit - b'0',
+                _ => return Err(ParseIndexPathError::InvalidOctant(segment.to_string())),
+            };
+            index_path = index_path.checked_put(octant.into()).ok_or(ParseIndexPathError::TooLong)?;
+        }
+        Ok(index_path)
+    }
+}
+
 impl std::fmt::Debug for IndexPath {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         let mut current = self.clone();
@@ -117,6 +349,57 @@ impl std::fmt::Debug for IndexPath {
     }
 }
 
+/// Serialized as the packed `u64` in binary formats, and as a path like `"/0/3/7"`
+/// listing the octants from the root in human-readable formats. The root is `"/"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for IndexPath {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let mut path = String::with_capacity(self.len() as usize * 2 + 1);
+            for dir in *self {
+                path.push('/');
+                path.push((dir as u8 + b'0').into());
+            }
+            if path.is_empty() {
+                path.push('/');
+            }
+            serializer.serialize_str(&path)
+        } else {
+            serializer.serialize_u64(self.0.get())
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for IndexPath {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct IndexPathVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for IndexPathVisitor {
+            type Value = IndexPath;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an index path")
+            }
+            fn visit_u64<E: serde::de::Error>(self, val: u64) -> Result<IndexPath, E> {
+                IndexPath::from_raw(val).ok_or_else(|| {
+                    E::invalid_value(serde::de::Unexpected::Unsigned(val), &self)
+                })
+            }
+            fn visit_str<E: serde::de::Error>(self, val: &str) -> Result<IndexPath, E> {
+                let index_path = if val.starts_with('/') { val.parse().ok() } else { None };
+                index_path.ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(val), &self))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(IndexPathVisitor)
+        } else {
+            deserializer.deserialize_u64(IndexPathVisitor)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,6 +416,16 @@ mod tests {
             path = path.push(Direction::FrontLeftBottom);
         }
         assert_eq!(path.len(), IndexPath::MAX_SIZE);
+        assert!(path.is_full());
+        assert_eq!(path.checked_push(Direction::RearRightTop), None);
+        assert_eq!(path.checked_put(Direction::RearRightTop), None);
+
+        let path = path.pop();
+        let pushed = path.checked_push(Direction::RearRightTop).unwrap();
+        assert_eq!(pushed.peek(), Direction::RearRightTop);
+        let put = path.checked_put(Direction::RearRightTop).unwrap();
+        assert_eq!(put.get(), Direction::RearRightTop);
+        assert_eq!(put.len(), IndexPath::MAX_SIZE);
     }
 
     #[test]
@@ -157,4 +450,279 @@ mod tests {
 
         assert_eq!(index_path.next(), None);
     }
+
+    #[test]
+    fn test_common_prefix() {
+        let a = IndexPath::from_cell((5, 2, 7), 3);
+        let b = IndexPath::from_cell((4, 2, 7), 3);
+        let prefix = a.common_prefix(b);
+        assert_eq!(prefix.len(), 2);
+        assert!(prefix.zip(a).all(|(p, a)| p == a));
+        assert_eq!(a.common_prefix(a), a);
+        assert_eq!(a.common_prefix(IndexPath::new()), IndexPath::new());
+        assert_eq!(a.common_prefix(IndexPath::from_cell((1, 0, 0), 1)), IndexPath::new());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut full = IndexPath::new();
+        for i in 0..IndexPath::MAX_SIZE {
+            full = full.put((i % 8).into());
+        }
+        for depth in 0..=IndexPath::MAX_SIZE {
+            let truncated = full.truncate(depth);
+            assert_eq!(truncated.len(), depth);
+            assert!(truncated.zip(full).all(|(a, b)| a == b));
+            assert!(truncated == full || truncated.is_ancestor_of(&full));
+            assert_eq!(truncated.truncate(depth + 1), truncated);
+        }
+        assert_eq!(full.truncate(0), IndexPath::new());
+        assert_eq!(full.truncate(IndexPath::MAX_SIZE + 1), full);
+        assert_eq!(IndexPath::new().truncate(3), IndexPath::new());
+    }
+
+    #[test]
+    fn test_concat() {
+        let a = IndexPath::from_cell((5, 2, 7), 3);
+        let b = IndexPath::new().put(Direction::RearLeftTop).put(Direction::FrontRightBottom);
+        let c = IndexPath::from_cell((1, 0, 1), 1);
+
+        assert_eq!(a.concat(&IndexPath::new()), a);
+        assert_eq!(IndexPath::new().concat(&a), a);
+        assert_eq!(a.concat(&b), a.put(Direction::RearLeftTop).put(Direction::FrontRightBottom));
+        assert_eq!(a.concat(&b).common_ancestor(&a), a);
+        assert_eq!(a.concat(&b).concat(&c), a.concat(&b.concat(&c)));
+        assert_eq!(a.concat(&b).len(), 5);
+
+        let mut long = IndexPath::new();
+        for _ in 0..IndexPath::MAX_SIZE - 3 {
+            long = long.push(Direction::RearRightTop);
+        }
+        assert!(long.concat(&a).is_full());
+    }
+
+    #[test]
+    #[should_panic(expected = "The index path is full")]
+    fn test_concat_overflow() {
+        let mut long = IndexPath::new();
+        for _ in 0..IndexPath::MAX_SIZE - 2 {
+            long = long.push(Direction::RearRightTop);
+        }
+        long.concat(&IndexPath::from_cell((5, 2, 7), 3));
+    }
+
+    #[test]
+    fn test_common_ancestor() {
+        let parent = IndexPath::from_cell((2, 1, 3), 2);
+        let child = parent.put(Direction::RearLeftTop);
+        let grandchild = child.put(Direction::FrontRightBottom);
+        let cousin = parent.put(Direction::FrontLeftTop).put(Direction::FrontRightBottom);
+
+        assert_eq!(child.common_ancestor(&child), child);
+        assert!(!child.is_ancestor_of(&child));
+        assert!(!child.is_descendant_of(&child));
+
+        assert_eq!(grandchild.common_ancestor(&parent), parent);
+        assert_eq!(parent.common_ancestor(&grandchild), parent);
+        assert!(parent.is_ancestor_of(&grandchild));
+        assert!(grandchild.is_descendant_of(&parent));
+        assert!(!grandchild.is_ancestor_of(&parent));
+        assert!(IndexPath::new().is_ancestor_of(&parent));
+
+        assert_eq!(grandchild.common_ancestor(&cousin), parent);
+        assert!(!cousin.is_descendant_of(&child));
+
+        let disjoint = IndexPath::from_cell((0, 0, 0), 3);
+        assert_eq!(disjoint.common_ancestor(&grandchild), IndexPath::new());
+        assert!(!disjoint.is_ancestor_of(&grandchild));
+        assert!(!disjoint.is_descendant_of(&grandchild));
+    }
+
+    #[test]
+    fn test_morton() {
+        assert_eq!(IndexPath::new().to_morton(), (0, 0));
+        assert_eq!(IndexPath::from_morton(0, 0), IndexPath::new());
+
+        let path = IndexPath::new().put(Direction::RearLeftBottom).put(Direction::FrontRightTop).put(Direction::RearRightTop);
+        assert_eq!(path.to_morton(), (0b010_101_111, 3));
+        assert_eq!(IndexPath::from_morton(0b010_101_111, 3), path);
+        // Leading zero octants are kept by the depth
+        assert_eq!(IndexPath::from_morton(0b101_111, 3), IndexPath::new().put(Direction::FrontLeftBottom).put(Direction::FrontRightTop).put(Direction::RearRightTop));
+
+        for &(cell, depth) in &[((5, 2, 7), 3), ((0, 0, 1), 1), ((1000, 3, 77), 10)] {
+            let path = IndexPath::from_cell(cell, depth);
+            let (code, len) = path.to_morton();
+            assert_eq!(len, depth);
+            assert_eq!(IndexPath::from_morton(code, len), path);
+        }
+        let mut full = IndexPath::new();
+        while !full.is_full() {
+            full = full.push(Direction::RearRightTop);
+        }
+        let (code, depth) = full.to_morton();
+        assert_eq!(IndexPath::from_morton(code, depth), full);
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the maximum index path length")]
+    fn test_from_morton_too_deep() {
+        IndexPath::from_morton(0, IndexPath::MAX_SIZE + 1);
+    }
+
+    #[test]
+    fn test_neighbor() {
+        let index_path = IndexPath::from_cell((3, 2, 5), 3);
+        assert_eq!(index_path.to_cell(), (3, 2, 5));
+        // The carry goes up to the root: the two cells are in different root octants
+        let right = index_path.neighbor(Face::Right).unwrap();
+        assert_eq!(right, IndexPath::from_cell((4, 2, 5), 3));
+        assert_ne!(right.peek(), index_path.peek());
+        assert_eq!(right.neighbor(Face::Left), Some(index_path));
+        // Within the same parent
+        assert_eq!(index_path.neighbor(Face::Rear), Some(IndexPath::from_cell((3, 3, 5), 3)));
+        assert_eq!(index_path.neighbor(Face::Bottom), Some(IndexPath::from_cell((3, 2, 4), 3)));
+
+        // On the border of the chunk
+        let corner = IndexPath::from_cell((7, 0, 7), 3);
+        assert_eq!(corner.neighbor(Face::Right), None);
+        assert_eq!(corner.neighbor(Face::Front), None);
+        assert_eq!(corner.neighbor(Face::Top), None);
+        assert_eq!(corner.neighbor(Face::Left), Some(IndexPath::from_cell((6, 0, 7), 3)));
+        assert_eq!(IndexPath::new().neighbor(Face::Right), None);
+    }
+
+    #[test]
+    fn test_double_ended() {
+        let mut path = IndexPath::new();
+        for i in 0..5 {
+            path = path.put(i.into());
+        }
+        let backwards: Vec<u8> = path.rev().map(|dir| dir as u8).collect();
+        assert_eq!(backwards, vec![4, 3, 2, 1, 0]);
+
+        let mut iter = path;
+        assert_eq!(iter.next(), Some(0.into()));
+        assert_eq!(iter.next_back(), Some(4.into()));
+        assert_eq!(iter.next(), Some(1.into()));
+        assert_eq!(iter.next_back(), Some(3.into()));
+        assert_eq!(iter.next_back(), Some(2.into()));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        assert_eq!(IndexPath::new().next_back(), None);
+    }
+
+    #[test]
+    fn test_from_str() {
+        let path = IndexPath::new().put(0.into()).put(3.into()).put(7.into());
+        assert_eq!("(Root)/0/3/7".parse(), Ok(path));
+        assert_eq!("0/3/7".parse(), Ok(path));
+        assert_eq!("(Root)".parse(), Ok(IndexPath::new()));
+        assert_eq!("".parse(), Ok(IndexPath::new()));
+        assert_eq!(format!("{:?}", path).parse(), Ok(path));
+
+        let mut full = IndexPath::new();
+        for i in 0..IndexPath::MAX_SIZE {
+            full = full.push((i % 8).into());
+        }
+        assert_eq!(format!("{:?}", full).parse(), Ok(full));
+        assert_eq!(format!("{:?}/1", full).parse::<IndexPath>(), Err(ParseIndexPathError::TooLong));
+
+        assert_eq!("0/8".parse::<IndexPath>(), Err(ParseIndexPathError::InvalidOctant("8".to_string())));
+        assert_eq!("0/x/1".parse::<IndexPath>(), Err(ParseIndexPathError::InvalidOctant("x".to_string())));
+        assert_eq!("0//1".parse::<IndexPath>(), Err(ParseIndexPathError::InvalidOctant("".to_string())));
+        assert_eq!("0/12".parse::<IndexPath>(), Err(ParseIndexPathError::InvalidOctant("12".to_string())));
+        assert!("(Root)0".parse::<IndexPath>().is_err());
+        assert_eq!(
+            ParseIndexPathError::InvalidOctant("9".to_string()).to_string(),
+            "invalid octant \"9\", expected a digit between 0 and 7"
+        );
+    }
+
+    #[test]
+    fn test_reversed() {
+        let mut index_path = IndexPath::new();
+        for i in &[3_u8, 1, 4, 1, 5] {
+            index_path = index_path.push((*i).into());
+        }
+        let mut reversed = index_path.reversed();
+        assert_eq!(reversed.len(), index_path.len());
+        let mut original = index_path;
+        while !original.is_empty() {
+            assert_eq!(reversed.get(), original.peek());
+            reversed = reversed.del();
+            original = original.pop();
+        }
+        assert!(reversed.is_empty());
+        assert_eq!(index_path.reversed().reversed(), index_path);
+    }
+
+    #[test]
+    fn test_from_cell() {
+        let index_path = IndexPath::from_cell((1, 2, 3), 2);
+        assert_eq!(index_path.len(), 2);
+        let bounds = crate::bounds::Bounds::from(index_path);
+        assert_eq!(bounds.get_position_with_gridsize(4), (1, 2, 3));
+        assert_eq!(bounds.get_width_with_gridsize(4), 1);
+    }
+
+    #[test]
+    fn test_from_raw() {
+        assert_eq!(IndexPath::from_raw(0), None);
+        assert_eq!(IndexPath::from_raw(0b10), None);
+        assert_eq!(IndexPath::from_raw(1), Some(IndexPath::new()));
+        let path = IndexPath::new().push(Direction::RearRightTop).push(Direction::FrontLeftBottom);
+        assert_eq!(IndexPath::from_raw(path.into()), Some(path));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let path = IndexPath::new().put(0.into()).put(3.into()).put(7.into());
+        assert_eq!(serde_json::to_string(&path).unwrap(), "\"/0/3/7\"");
+        assert_eq!(serde_json::from_str::<IndexPath>("\"/0/3/7\"").unwrap(), path);
+        assert_eq!(serde_json::to_string(&IndexPath::new()).unwrap(), "\"/\"");
+        assert_eq!(serde_json::from_str::<IndexPath>("\"/\"").unwrap(), IndexPath::new());
+        assert!(serde_json::from_str::<IndexPath>("\"/0/8\"").is_err());
+        assert!(serde_json::from_str::<IndexPath>("\"0/3\"").is_err());
+        assert!(serde_json::from_str::<IndexPath>("\"/0//3\"").is_err());
+
+        let bytes = bincode::serialize(&path).unwrap();
+        assert_eq!(bytes, bincode::serialize(&Into::<u64>::into(path)).unwrap());
+        assert_eq!(bincode::deserialize::<IndexPath>(&bytes).unwrap(), path);
+        assert!(bincode::deserialize::<IndexPath>(&bincode::serialize(&0_u64).unwrap()).is_err());
+        assert!(bincode::deserialize::<IndexPath>(&bincode::serialize(&0b10_u64).unwrap()).is_err());
+
+        let mut full = IndexPath::new();
+        while !full.is_full() {
+            full = full.push(Direction::RearLeftTop);
+        }
+        let json = serde_json::to_string(&full).unwrap();
+        assert_eq!(serde_json::from_str::<IndexPath>(&json).unwrap(), full);
+        let too_long = format!("\"{}\"", "/6".repeat(IndexPath::MAX_SIZE as usize + 1));
+        assert!(serde_json::from_str::<IndexPath>(&too_long).is_err());
+    }
+
+    #[test]
+    fn test_ord() {
+        let parent = IndexPath::new().put(Direction::RearLeftBottom).put(Direction::FrontRightTop);
+        let mut previous = parent;
+        for i in 0..8 {
+            let child = parent.put(i.into());
+            assert!(parent < child);
+            assert!(previous < child);
+            assert!(child < parent.del().put(Direction::RearLeftTop));
+            assert!(child < IndexPath::new().put(Direction::RearRightTop));
+            previous = child;
+        }
+        assert!(IndexPath::new() < parent);
+        assert_eq!(Ord::cmp(&parent, &parent), std::cmp::Ordering::Equal);
+
+        let mut chunk: crate::chunk::Chunk<u16> = crate::chunk::Chunk::new();
+        chunk.set(parent.put(Direction::FrontLeftTop), 1);
+        chunk.set(IndexPath::new().put(Direction::RearRightTop).put(Direction::FrontLeftBottom), 2);
+        let leaves: Vec<_> = chunk.iter_leaf().map(|voxel| voxel.get_index_path()).collect();
+        let sorted: std::collections::BTreeSet<_> = leaves.iter().rev().cloned().collect();
+        assert_eq!(sorted.into_iter().collect::<Vec<_>>(), leaves);
+    }
 }
@@ -0,0 +1,99 @@
+use crate::chunk::Chunk;
+use crate::node::Node;
+use crate::direction::Direction;
+
+enum Pending<'a, T> {
+    // A node whose octants are `size` cells wide
+    Node(&'a Node<T>, (u32, u32, u32), u32),
+    // A region of `size` cells per side sharing a value
+    Region(&'a T, (u32, u32, u32), u32),
+}
+
+pub struct ChunkCellIterator<'a, T> {
+    stack: Vec<Pending<'a, T>>,
+    region: Option<(&'a T, (u32, u32, u32), u32)>,
+    cell: u32, // Next cell to emit in the current region
+}
+
+impl<'a, T: Clone> Iterator for ChunkCellIterator<'a, T> {
+    type Item = ((u32, u32, u32), T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((value, origin, size)) = self.region {
+                if self.cell < size * size * size {
+                    let cell = self.cell;
+                    self.cell += 1;
+                    let coords = (
+                        origin.0 + cell / (size * size),
+                        origin.1 + cell / size % size,
+                        origin.2 + cell % size,
+                    );
+                    return Some((coords, value.clone()));
+                }
+                self.region = None;
+            }
+            match self.stack.pop()? {
+                Pending::Region(value, origin, size) => {
+                    self.region = Some((value, origin, size));
+                    self.cell = 0;
+                }
+                Pending::Node(node, origin, size) => {
+                    // Pushed in reverse so that the octants are visited in direction order
+                    for dir in (0..8_u8).rev().map(Direction::from) {
+                        let offset = dir.breakdown();
+                        let location = (
+                            origin.0 + offset.0 as u32 * size,
+                            origin.1 + offset.1 as u32 * size,
+                            origin.2 + offset.2 as u32 * size,
+                        );
+                        match &node.children[dir] {
+                            Some(child) if size > 1 => self.stack.push(Pending::Node(child, location, size / 2)),
+                            _ => self.stack.push(Pending::Region(&node.data[dir], location, size)),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: Clone, M> Chunk<T, M> {
+    /// Iterates the cells of a grid subdivided `depth` times, with the same values as
+    /// `Grid::new(chunk, depth)` but without allocating the whole grid.
+    pub fn iter_cells_at(&self, depth: u8) -> ChunkCellIterator<'_, T> {
+        assert!(depth > 0 && depth < 32);
+        ChunkCellIterator {
+            stack: vec![Pending::Node(&self.root, (0, 0, 0), 1 << (depth - 1))],
+            region: None,
+            cell: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::grid::Grid;
+    use crate::index_path::IndexPath;
+
+    #[test]
+    fn test_iter_cells_at() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 0, 0), 1), 1);
+        chunk.set(IndexPath::from_cell((2, 5, 1), 3), 2);
+        chunk.set(IndexPath::from_cell((7, 7, 6), 3), 3);
+
+        for depth in 1..5 {
+            let grid = Grid::new(&chunk, depth);
+            let mut expected: Vec<((u32, u32, u32), u16)> = grid.iter()
+                .map(|((x, y, z), value)| ((x as u32, y as u32, z as u32), *value))
+                .collect();
+            let mut cells: Vec<_> = chunk.iter_cells_at(depth).collect();
+            assert_eq!(cells.len(), 1 << (3 * depth));
+            expected.sort();
+            cells.sort();
+            assert_eq!(cells, expected);
+        }
+    }
+}
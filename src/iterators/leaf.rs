@@ -4,9 +4,9 @@ use crate::voxel::Voxel;
 use crate::index_path::IndexPath;
 use crate::bounds::Bounds;
 use crate::node::Node;
+use std::iter::FusedIterator;
 
 pub struct ChunkLeafIterator<'a, T> {
-    chunk: &'a Chunk<T>,
     stack: Vec<(Direction, &'a Node<T>)>,
     index_path: IndexPath,
     bounds: Bounds,
@@ -60,17 +60,49 @@ impl<'a, T> Iterator for ChunkLeafIterator<'a, T> {
             }
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Every octant not visited yet holds at least one leaf
+        let current = 8 - self.dir.min(8) as usize;
+        let ancestors: usize = self.stack.iter()
+            .skip(1)
+            .map(|(fromdir, _)| 7 - *fromdir as usize)
+            .sum();
+        if self.stack.is_empty() {
+            (0, Some(0))
+        } else {
+            (current + ancestors, None)
+        }
+    }
 }
-impl<T> Chunk<T> {
+
+// Once the stack is empty, it stays empty
+impl<'a, T> FusedIterator for ChunkLeafIterator<'a, T> {}
+impl<T, M> Chunk<T, M> {
     pub fn iter_leaf(&self) -> ChunkLeafIterator<T> {
         ChunkLeafIterator {
-            chunk: &self,
             stack: vec![(0.into(), &self.root)],
             index_path: IndexPath::new(),
             bounds: Bounds::new(),
             dir: 0
         }
     }
+    /// Iterates all leaves with mutable access to their values, in the same order as `iter_leaf`.
+    /// Changing the values may leave octants that could be merged; call `canonicalize` afterwards.
+    pub fn iter_leaf_mut(&mut self) -> impl Iterator<Item = (IndexPath, &mut T)> {
+        fn visit<'a, T>(node: &'a mut Node<T>, index_path: IndexPath, leaves: &mut Vec<(IndexPath, &'a mut T)>) {
+            for (i, (child, data)) in node.children.data.iter_mut().zip(node.data.data.iter_mut()).enumerate() {
+                let child_path = index_path.put((i as u8).into());
+                match child {
+                    Some(subnode) => visit(subnode, child_path, leaves),
+                    None => leaves.push((child_path, data)),
+                }
+            }
+        }
+        let mut leaves = Vec::new();
+        visit(&mut self.root, IndexPath::new(), &mut leaves);
+        leaves.into_iter()
+    }
 }
 
 #[cfg(test)]
@@ -121,6 +153,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_leaf_iterator_fused() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::new().push(Direction::RearRightTop).push(Direction::FrontLeftBottom), 1);
+        let total = chunk.iter_leaf().count();
+        assert_eq!(total, 15);
+
+        let mut iter = chunk.iter_leaf();
+        for remaining in (1..=total).rev() {
+            let (lower, upper) = iter.size_hint();
+            assert!(lower >= 1 && lower <= remaining);
+            assert_eq!(upper, None);
+            assert!(iter.next().is_some());
+        }
+        for _ in 0..3 {
+            assert!(iter.next().is_none());
+            assert_eq!(iter.size_hint(), (0, Some(0)));
+        }
+
+        let leaves: Vec<_> = chunk.iter_leaf().collect();
+        assert_eq!(leaves.len(), total);
+        assert!(leaves.capacity() < 2 * total);
+    }
+
+    #[test]
+    fn test_leaf_iterator_mut() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::new().push(Direction::RearRightTop).push(Direction::FrontLeftBottom), 1);
+        chunk.set(IndexPath::new().push(Direction::FrontLeftBottom), 2);
+
+        let expected: Vec<_> = chunk.iter_leaf()
+            .map(|voxel| (voxel.get_index_path(), *voxel.get_value()))
+            .collect();
+        let paths: Vec<_> = chunk.iter_leaf_mut().map(|(index_path, _)| index_path).collect();
+        assert_eq!(paths, expected.iter().map(|(index_path, _)| *index_path).collect::<Vec<_>>());
+
+        for (_, value) in chunk.iter_leaf_mut() {
+            *value += 10;
+        }
+        for (voxel, (index_path, value)) in chunk.iter_leaf().zip(expected.iter()) {
+            assert_eq!(voxel.get_index_path(), *index_path);
+            assert_eq!(*voxel.get_value(), value + 10);
+        }
+
+        for (_, value) in chunk.iter_leaf_mut() {
+            *value = 5;
+        }
+        assert_eq!(chunk.iter_leaf().count(), 15);
+        chunk.canonicalize();
+        assert_eq!(chunk.uniform_value(), Some(&5));
+    }
+
     #[test]
     fn test_leaf_iterator_cube_generator() {
         let world_builder: WorldBuilder<u32, _> = WorldBuilder::new(
@@ -1 +1,3 @@
 mod leaf;
+mod surface;
+mod cells;
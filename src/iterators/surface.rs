@@ -0,0 +1,93 @@
+use crate::chunk::Chunk;
+use crate::direction::Face;
+use crate::index_path::IndexPath;
+use crate::bounds::Bounds;
+use crate::node::Node;
+use crate::VoxelData;
+
+/// Whether any leaf in the slot on the index path satisfies `pred`.
+/// When the slot is subdivided, only the leaves touching `face` are considered.
+fn any_on_face<T, F: Fn(&T) -> bool>(node: &Node<T>, index_path: IndexPath, face: Face, pred: &F) -> bool {
+    let dir = index_path.peek();
+    let index_path = index_path.pop();
+    match &node.children[dir] {
+        Some(child) if index_path.is_empty() => any_leaf_on_face(child, face, pred),
+        Some(child) => any_on_face(child, index_path, face, pred),
+        None => pred(&node.data[dir]),
+    }
+}
+
+fn any_leaf_on_face<T, F: Fn(&T) -> bool>(node: &Node<T>, face: Face, pred: &F) -> bool {
+    node.children.enumerate()
+        .filter(|(dir, _)| face.contains(*dir))
+        .any(|(dir, child)| match child {
+            Some(child) => any_leaf_on_face(child, face, pred),
+            None => pred(&node.data[dir]),
+        })
+}
+
+impl<T: VoxelData, M> Chunk<T, M> {
+    /// Whether the face of the cell at `depth` touches any empty leaf.
+    /// Faces on the chunk border are always exposed.
+    pub(crate) fn is_face_exposed(&self, cell: (u64, u64, u64), depth: u8, face: Face) -> bool {
+        let size = 1_i64 << depth;
+        let offset = face.offset();
+        let neighbor = (
+            cell.0 as i64 + offset.0 as i64,
+            cell.1 as i64 + offset.1 as i64,
+            cell.2 as i64 + offset.2 as i64,
+        );
+        if [neighbor.0, neighbor.1, neighbor.2].iter().any(|&i| i < 0 || i >= size) {
+            return true;
+        }
+        let index_path = IndexPath::from_cell((neighbor.0 as u32, neighbor.1 as u32, neighbor.2 as u32), depth);
+        any_on_face(&self.root, index_path, face.opposite(), &|value: &T| value.is_empty())
+    }
+
+    /// Iterates the faces of solid leaves that touch empty space.
+    pub fn iter_surface_faces(&self) -> impl Iterator<Item = (Bounds, Face)> + '_ {
+        self.iter_leaf()
+            .filter(|voxel| !voxel.get_value().is_empty())
+            .flat_map(move |voxel| {
+                let depth = voxel.get_index_path().len();
+                let bounds = voxel.get_bounds().clone();
+                let cell = bounds.get_position_with_gridsize(1 << depth);
+                Face::ALL.iter()
+                    .filter(move |face| self.is_face_exposed(cell, depth, **face))
+                    .map(move |face| (bounds.clone(), *face))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Copy, Clone, Default, PartialEq, Debug)]
+    struct Solid(bool);
+    impl VoxelData for Solid {
+        fn is_empty(&self) -> bool {
+            !self.0
+        }
+    }
+
+    #[test]
+    fn test_single_leaf() {
+        let mut chunk: Chunk<Solid> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 1, 1), 2), Solid(true));
+        let faces: Vec<_> = chunk.iter_surface_faces().collect();
+        assert_eq!(faces.len(), 6);
+        for face in &Face::ALL {
+            assert!(faces.iter().any(|(_, f)| f == face));
+        }
+    }
+
+    #[test]
+    fn test_shared_face() {
+        let mut chunk: Chunk<Solid> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 1, 1), 2), Solid(true));
+        chunk.set(IndexPath::from_cell((2, 1, 1), 2), Solid(true));
+        let faces: Vec<_> = chunk.iter_surface_faces().collect();
+        assert_eq!(faces.len(), 10);
+    }
+}
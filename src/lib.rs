@@ -3,6 +3,7 @@
 #![feature(alloc_layout_extra)]
 #![feature(maybe_uninit_extra)]
 #![feature(maybe_uninit_uninit_array)]
+#![feature(min_const_generics)]
 
 pub mod direction;
 pub mod node;
@@ -10,12 +11,105 @@ pub mod index_path;
 pub mod chunk;
 pub mod world;
 pub mod world_builder;
+pub mod chunk_builder;
 pub mod bounds;
 pub mod voxel;
 pub mod mesher;
 pub mod grid;
+pub mod density;
+pub mod bytes;
+mod lighting;
+pub mod gpu;
+pub mod aabb;
+pub mod cursor;
 mod iterators;
+#[cfg(feature = "vox")]
+pub mod vox;
 
 pub trait VoxelData: Clone + Default {
     fn is_empty(&self) -> bool;
+    /// Whether the voxel hides what's behind it. Solid voxels that aren't opaque,
+    /// like glass or water, still let the faces behind them show.
+    fn is_opaque(&self) -> bool {
+        !self.is_empty()
+    }
+    /// Signed density of the voxel, positive inside the surface.
+    fn density(&self) -> f32 {
+        if self.is_empty() { -1.0 } else { 1.0 }
+    }
+    fn material_id(&self) -> u16 {
+        0
+    }
+}
+
+/// Voxel wrapper for the common case where the default value stands for empty space.
+///
+/// ```
+/// use octree::{EmptyIf, VoxelData};
+///
+/// let air: EmptyIf<u8> = Default::default();
+/// assert!(air.is_empty());
+/// assert!(!EmptyIf(3u8).is_empty());
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct EmptyIf<T>(pub T);
+
+impl<T: Clone + Default + PartialEq> VoxelData for EmptyIf<T> {
+    fn is_empty(&self) -> bool {
+        self.0 == T::default()
+    }
+}
+
+impl<T> From<T> for EmptyIf<T> {
+    fn from(value: T) -> Self {
+        EmptyIf(value)
+    }
+}
+
+impl<T> std::ops::Deref for EmptyIf<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// Linear interpolation between two values, with `t` going from 0 at `self` to 1 at `other`.
+pub trait Lerp {
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Copy, Clone, Debug, Default, PartialEq)]
+    enum Block {
+        #[default]
+        Air,
+        Dirt,
+    }
+
+    #[test]
+    fn test_empty_if() {
+        let air = EmptyIf(Block::Air);
+        let dirt: EmptyIf<Block> = Block::Dirt.into();
+        assert!(air.is_empty());
+        assert!(EmptyIf::<Block>::default().is_empty());
+        assert!(!dirt.is_empty());
+        assert!(dirt.is_opaque());
+        assert_eq!(*dirt, Block::Dirt);
+        assert_eq!(air.density(), -1.0);
+    }
 }
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+use crate::chunk::Chunk;
+use crate::bounds::Bounds;
+use crate::direction::Face;
+use crate::grid::Grid;
+use crate::index_path::IndexPath;
+use crate::VoxelData;
+
+impl<T: VoxelData, M> Chunk<T, M> {
+    /// Flood light from the sources, losing one level per step and stopping at solid voxels.
+    /// The light is computed on a grid as deep as the deepest leaf or source.
+    pub fn propagate_light(&self, sources: &[(IndexPath, u8)]) -> Grid<u8> {
+        let lod = sources.iter()
+            .map(|(index_path, _)| index_path.len())
+            .fold(self.max_depth(), u8::max);
+        let solids = Grid::new(self, lod);
+        let mut light = Grid::filled(lod, 0_u8);
+        let size = light.get_size() as u64;
+
+        let mut queue = VecDeque::new();
+        for (index_path, level) in sources {
+            let bounds = Bounds::from(*index_path);
+            let position = bounds.get_position_with_gridsize(size);
+            let width = bounds.get_width_with_gridsize(size);
+            for x in position.0..position.0 + width {
+                for y in position.1..position.1 + width {
+                    for z in position.2..position.2 + width {
+                        let cell = (x as usize, y as usize, z as usize);
+                        if light[cell] < *level {
+                            light[cell] = *level;
+                            queue.push_back(cell);
+                        }
+                    }
+                }
+            }
+        }
+
+        while let Some(cell) = queue.pop_front() {
+            let level = light[cell];
+            if level <= 1 {
+                continue;
+            }
+            for face in &Face::ALL {
+                let offset = face.offset();
+                let neighbor = (
+                    cell.0 as i64 + offset.0 as i64,
+                    cell.1 as i64 + offset.1 as i64,
+                    cell.2 as i64 + offset.2 as i64,
+                );
+                if [neighbor.0, neighbor.1, neighbor.2].iter().any(|&i| i < 0 || i >= size as i64) {
+                    continue;
+                }
+                let neighbor = (neighbor.0 as usize, neighbor.1 as usize, neighbor.2 as usize);
+                if solids[neighbor].is_empty() && light[neighbor] < level - 1 {
+                    light[neighbor] = level - 1;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        light
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::density::DensityVoxel;
+
+    #[test]
+    fn test_propagate_light() {
+        let mut chunk: Chunk<DensityVoxel> = Chunk::new();
+        for y in 0..8 {
+            for z in 0..8 {
+                chunk.set(IndexPath::from_cell((4, y, z), 3), DensityVoxel::new(1, 0));
+            }
+        }
+        let light = chunk.propagate_light(&[(IndexPath::from_cell((1, 3, 3), 3), 10)]);
+        assert_eq!(light.get_lod(), 3);
+        assert_eq!(light[(1, 3, 3)], 10);
+        assert_eq!(light[(0, 3, 3)], 9);
+        assert_eq!(light[(2, 3, 3)], 9);
+        assert_eq!(light[(3, 3, 3)], 8);
+        assert_eq!(light[(3, 5, 4)], 5);
+        assert_eq!(light[(1, 0, 0)], 4);
+
+        // The wall blocks the light entirely
+        for y in 0..8 {
+            for z in 0..8 {
+                for x in 4..8 {
+                    assert_eq!(light[(x, y, z)], 0);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use super::Mesh;
+
+/// Topology of a mesh after welding vertices that share the same position.
+pub struct MeshAdjacency {
+    /// Welded vertex of each entry in `Mesh::vertices`
+    pub vertex_map: Vec<u32>,
+    /// Triangles expressed in welded vertices
+    pub triangles: Vec<[u32; 3]>,
+    /// Triangles sharing each undirected edge, keyed by the sorted vertex pair
+    pub edges: HashMap<(u32, u32), Vec<u32>>,
+    /// Triangles around each welded vertex
+    pub vertex_triangles: Vec<Vec<u32>>,
+}
+
+impl MeshAdjacency {
+    /// The triangle on the other side of the `edge`th edge of `triangle`,
+    /// if exactly one other triangle shares that edge.
+    pub fn opposite(&self, triangle: u32, edge: usize) -> Option<u32> {
+        let vertices = self.triangles[triangle as usize];
+        let key = edge_key(vertices[edge], vertices[(edge + 1) % 3]);
+        match self.edges[&key].as_slice() {
+            [a, b] if *a == triangle => Some(*b),
+            [a, b] if *b == triangle => Some(*a),
+            _ => None,
+        }
+    }
+}
+
+fn edge_key(a: u32, b: u32) -> (u32, u32) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+impl Mesh {
+    pub fn build_adjacency(&self) -> MeshAdjacency {
+        let mut welded: HashMap<(u32, u32, u32), u32> = HashMap::new();
+        let vertex_map: Vec<u32> = self.vertices.iter()
+            .map(|vertex| {
+                // Adding 0.0 turns -0.0 into 0.0 so both weld together
+                let key = (
+                    (vertex.x() + 0.0).to_bits(),
+                    (vertex.y() + 0.0).to_bits(),
+                    (vertex.z() + 0.0).to_bits(),
+                );
+                let next = welded.len() as u32;
+                *welded.entry(key).or_insert(next)
+            })
+            .collect();
+
+        let mut adjacency = MeshAdjacency {
+            vertex_map,
+            triangles: Vec::with_capacity(self.indices.len() / 3),
+            edges: HashMap::new(),
+            vertex_triangles: vec![Vec::new(); welded.len()],
+        };
+        for (i, triangle) in self.indices.chunks_exact(3).enumerate() {
+            let i = i as u32;
+            let vertices = [
+                adjacency.vertex_map[triangle[0] as usize],
+                adjacency.vertex_map[triangle[1] as usize],
+                adjacency.vertex_map[triangle[2] as usize],
+            ];
+            for edge in 0..3 {
+                adjacency.edges.entry(edge_key(vertices[edge], vertices[(edge + 1) % 3]))
+                    .or_default()
+                    .push(i);
+                adjacency.vertex_triangles[vertices[edge] as usize].push(i);
+            }
+            adjacency.triangles.push(vertices);
+        }
+        adjacency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam as math;
+
+    #[test]
+    fn test_tetrahedron() {
+        let corners = [
+            math::Vec3::new(0.0, 0.0, 0.0),
+            math::Vec3::new(1.0, 0.0, 0.0),
+            math::Vec3::new(0.0, 1.0, 0.0),
+            math::Vec3::new(0.0, 0.0, 1.0),
+        ];
+        // Unwelded, three vertices per triangle like the mesher emits them
+        let mut mesh = Mesh { vertices: vec![], indices: vec![], normals: vec![] };
+        for triangle in &[[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]] {
+            for &corner in triangle {
+                mesh.indices.push(mesh.vertices.len() as u32);
+                mesh.vertices.push(corners[corner]);
+            }
+        }
+
+        let adjacency = mesh.build_adjacency();
+        assert_eq!(adjacency.vertex_triangles.len(), 4);
+        assert!(adjacency.vertex_triangles.iter().all(|fan| fan.len() == 3));
+        assert_eq!(adjacency.edges.len(), 6);
+        assert!(adjacency.edges.values().all(|faces| faces.len() == 2));
+        for triangle in 0..4 {
+            for edge in 0..3 {
+                let opposite = adjacency.opposite(triangle, edge).unwrap();
+                assert_ne!(opposite, triangle);
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+/// Order in which the vertices of each emitted triangle are listed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// How vertex normals are computed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NormalMode {
+    /// No normals are emitted.
+    None,
+    /// Normals point against the density gradient, estimated with central differences on the grid.
+    /// `GreedyMesher` emits the flat normal of each face instead.
+    Gradient,
+}
+
+/// Where in its voxel each grid sample sits, which places the lattice the surface is extracted on.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SampleMode {
+    /// Samples sit at the minimum corner of their voxel, so sample `i` is at `i`.
+    Corner,
+    /// Samples sit at the center of their voxel, half a voxel further along each axis.
+    Center,
+}
+
+impl SampleMode {
+    /// Position of the sample within its voxel, in voxels.
+    pub(crate) fn offset(&self) -> f32 {
+        match self {
+            SampleMode::Corner => 0.0,
+            SampleMode::Center => 0.5,
+        }
+    }
+}
+
+/// Options shared by the meshers. Built with chained setters on top of the defaults:
+/// `MesherConfig::new().scale(2.0).winding(Winding::Clockwise)`
+#[derive(Clone, Debug)]
+pub struct MesherConfig {
+    pub(crate) winding: Winding,
+    pub(crate) scale: f32,
+    pub(crate) normals: NormalMode,
+    pub(crate) quantize: Option<f32>,
+    pub(crate) sample_mode: SampleMode,
+}
+
+impl Default for MesherConfig {
+    fn default() -> Self {
+        MesherConfig {
+            winding: Winding::CounterClockwise,
+            scale: 1.0,
+            normals: NormalMode::None,
+            quantize: None,
+            sample_mode: SampleMode::Corner,
+        }
+    }
+}
+
+impl MesherConfig {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    pub fn winding(mut self, winding: Winding) -> Self {
+        self.winding = winding;
+        self
+    }
+    /// Uniform scale applied to every emitted vertex.
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+    pub fn normals(mut self, normals: NormalMode) -> Self {
+        self.normals = normals;
+        self
+    }
+    pub fn sample_mode(mut self, sample_mode: SampleMode) -> Self {
+        self.sample_mode = sample_mode;
+        self
+    }
+    /// Snap every emitted vertex, after scaling, to multiples of `step`.
+    /// Keeps the vertices identical across platforms with different float rounding.
+    pub fn quantize(mut self, step: f32) -> Self {
+        assert!(step > 0.0);
+        self.quantize = Some(step);
+        self
+    }
+}
@@ -0,0 +1,275 @@
+use super::{Mesher, Mesh, MesherConfig, NormalMode, SplitMesh, Winding};
+use crate::world::{ChunkCoordinates, World};
+use crate::grid::Grid;
+use crate::VoxelData;
+use glam as math;
+
+/// Blocky mesher merging coplanar faces of the same kind into rectangles.
+/// A voxel shows a face towards a neighbor that isn't opaque, unless both are see-through
+/// and solid, like two blocks of glass. Everything outside of the chunk is considered empty.
+pub struct GreedyMesher<'a, T> {
+    world: &'a World<T>,
+    config: MesherConfig,
+}
+
+impl<'a, T> GreedyMesher<'a, T> {
+    pub fn with_config(world: &'a World<T>, config: MesherConfig) -> Self {
+        GreedyMesher {
+            world,
+            config,
+        }
+    }
+    pub fn config(&self) -> &MesherConfig {
+        &self.config
+    }
+}
+
+impl<'a, T: VoxelData> Mesher<'a, T> for GreedyMesher<'a, T> {
+    fn new(world: &'a World<T>) -> Self {
+        Self::with_config(world, Default::default())
+    }
+
+    fn build(&self, chunk_location: &ChunkCoordinates, lod: u8) -> Mesh {
+        self.build_split(chunk_location, lod).merge()
+    }
+
+    fn build_split(&self, chunk_location: &ChunkCoordinates, lod: u8) -> SplitMesh {
+        let chunk = self.world.get_chunk_ref(chunk_location)
+            .unwrap_or_else(|| panic!("Trying to build a chunk that doesn't exist at {:?}", chunk_location));
+        let grid = Grid::new(chunk, lod);
+        let size = grid.get_size();
+
+        let mut split = SplitMesh::default();
+        // Faces are merged when they share the material and the opacity
+        let mut mask: Vec<Option<(u16, bool)>> = vec![None; size * size];
+        for axis in 0..3 {
+            // The two other axes, in cyclic order so that u x v points along the axis
+            let u_axis = (axis + 1) % 3;
+            let v_axis = (axis + 2) % 3;
+            for &positive in &[false, true] {
+                for slice in 0..size {
+                    for u in 0..size {
+                        for v in 0..size {
+                            let mut position = [0; 3];
+                            position[axis] = slice;
+                            position[u_axis] = u;
+                            position[v_axis] = v;
+                            let voxel = &grid[(position[0], position[1], position[2])];
+                            let neighbor = match (positive, slice) {
+                                (false, 0) => None,
+                                (true, slice) if slice + 1 == size => None,
+                                _ => {
+                                    if positive { position[axis] += 1 } else { position[axis] -= 1 }
+                                    Some(&grid[(position[0], position[1], position[2])])
+                                }
+                            };
+                            mask[u * size + v] = if is_face_visible(voxel, neighbor) {
+                                Some((voxel.material_id(), voxel.is_opaque()))
+                            } else {
+                                None
+                            };
+                        }
+                    }
+                    let plane = if positive { slice + 1 } else { slice };
+                    self.emit_rectangles(&mut split, &mut mask, size, plane, [axis, u_axis, v_axis], positive);
+                }
+            }
+        }
+        split
+    }
+}
+
+fn is_face_visible<T: VoxelData>(voxel: &T, neighbor: Option<&T>) -> bool {
+    if voxel.is_empty() {
+        return false;
+    }
+    match neighbor {
+        None => true,
+        Some(neighbor) => !neighbor.is_opaque() && (voxel.is_opaque() || neighbor.is_empty()),
+    }
+}
+
+impl<'a, T> GreedyMesher<'a, T> {
+    /// Cover the faces of the mask with rectangles, clearing it on the way.
+    /// `axes` lists the axis the faces point along, followed by the axes of the mask.
+    fn emit_rectangles(&self, split: &mut SplitMesh, mask: &mut [Option<(u16, bool)>], size: usize, plane: usize, axes: [usize; 3], positive: bool) {
+        for u in 0..size {
+            let mut v = 0;
+            while v < size {
+                let key = match mask[u * size + v] {
+                    Some(key) => key,
+                    None => {
+                        v += 1;
+                        continue;
+                    }
+                };
+                let mut height = 1;
+                while v + height < size && mask[u * size + v + height] == Some(key) {
+                    height += 1;
+                }
+                let mut width = 1;
+                while u + width < size && (v..v + height).all(|j| mask[(u + width) * size + j] == Some(key)) {
+                    width += 1;
+                }
+                for i in u..u + width {
+                    for j in v..v + height {
+                        mask[i * size + j] = None;
+                    }
+                }
+                let (_, opaque) = key;
+                let mesh = if opaque { &mut split.opaque } else { &mut split.transparent };
+                self.emit_quad(mesh, plane, (u, v), (width, height), axes, positive);
+                v += height;
+            }
+        }
+    }
+
+    fn emit_quad(&self, mesh: &mut Mesh, plane: usize, origin: (usize, usize), extent: (usize, usize), axes: [usize; 3], positive: bool) {
+        let [axis, u_axis, v_axis] = axes;
+        let corner = |u: usize, v: usize| {
+            let mut position = [0.0; 3];
+            position[axis] = plane as f32;
+            position[u_axis] = u as f32;
+            position[v_axis] = v as f32;
+            let mut vertex = math::Vec3::new(position[0], position[1], position[2]) * self.config.scale;
+            if let Some(step) = self.config.quantize {
+                vertex = (vertex / step).round() * step;
+            }
+            vertex
+        };
+        let (u, v) = origin;
+        let (width, height) = extent;
+        // Counter clockwise seen from the side the face points to
+        let mut corners = [
+            corner(u, v),
+            corner(u + width, v),
+            corner(u + width, v + height),
+            corner(u, v + height),
+        ];
+        if !positive {
+            corners.reverse();
+        }
+        let base = mesh.vertices.len() as u32;
+        mesh.vertices.extend_from_slice(&corners);
+        // Any mode other than None gets the flat normal of the face, as blocks have no gradient
+        if self.config.normals != NormalMode::None {
+            let mut normal = [0.0; 3];
+            normal[axis] = if positive { 1.0 } else { -1.0 };
+            let normal = math::Vec3::new(normal[0], normal[1], normal[2]);
+            mesh.normals.extend_from_slice(&[normal; 4]);
+        }
+        for triangle in &[[0, 1, 2], [0, 2, 3]] {
+            let triangle = match self.config.winding {
+                Winding::CounterClockwise => *triangle,
+                Winding::Clockwise => [triangle[0], triangle[2], triangle[1]],
+            };
+            mesh.indices.extend(triangle.iter().map(|i| base + i));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::index_path::IndexPath;
+
+    #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+    enum Block {
+        #[default]
+        Air,
+        Stone,
+        Glass,
+    }
+    impl VoxelData for Block {
+        fn is_empty(&self) -> bool {
+            *self == Block::Air
+        }
+        fn is_opaque(&self) -> bool {
+            *self == Block::Stone
+        }
+        fn material_id(&self) -> u16 {
+            *self as u16
+        }
+    }
+
+    fn world_with(cells: &[((u32, u32, u32), Block)], depth: u8) -> World<Block> {
+        let mut chunk: Chunk<Block> = Chunk::new();
+        for (cell, block) in cells {
+            chunk.set(IndexPath::from_cell(*cell, depth), *block);
+        }
+        let mut world = World::new();
+        world.insert_chunk(ChunkCoordinates::new(), chunk);
+        world
+    }
+
+    // Number of unit faces covered by the quads of the mesh lying on the plane
+    fn area_on_plane(mesh: &Mesh, axis: usize, plane: f32) -> f32 {
+        mesh.indices().chunks(3)
+            .map(|triangle| {
+                let (a, b, c) = (mesh.vertices()[triangle[0] as usize], mesh.vertices()[triangle[1] as usize], mesh.vertices()[triangle[2] as usize]);
+                let on_plane = [a, b, c].iter().all(|vertex| [vertex.x(), vertex.y(), vertex.z()][axis] == plane);
+                if on_plane { (b - a).cross(c - a).length() / 2.0 } else { 0.0 }
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_merged_faces() {
+        // A 2x2x2 block of stone is a cube with a single quad per side
+        let world = world_with(&[((0, 0, 0), Block::Stone)], 1);
+        let mesh = GreedyMesher::new(&world).build(&ChunkCoordinates::new(), 2);
+        assert_eq!(mesh.indices().len(), 6 * 2 * 3);
+        assert_eq!(mesh.vertices().len(), 6 * 4);
+        assert_eq!(area_on_plane(&mesh, 0, 2.0), 4.0);
+
+        let config = MesherConfig::new().normals(NormalMode::Gradient);
+        let mesh = GreedyMesher::with_config(&world, config).build(&ChunkCoordinates::new(), 2);
+        assert_eq!(mesh.normals().len(), mesh.vertices().len());
+        for triangle in mesh.indices().chunks(3) {
+            let (a, b, c) = (mesh.vertices()[triangle[0] as usize], mesh.vertices()[triangle[1] as usize], mesh.vertices()[triangle[2] as usize]);
+            // Counter clockwise triangles face along their normal, away from the cube
+            let facing = (b - a).cross(c - a).normalize();
+            assert_eq!(facing, mesh.normals()[triangle[0] as usize]);
+            assert!(facing.dot(a - math::Vec3::one()) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_transparent_faces() {
+        // Glass right next to stone along x
+        let world = world_with(&[((0, 0, 0), Block::Stone), ((1, 0, 0), Block::Glass)], 2);
+        let mesh = GreedyMesher::new(&world).build(&ChunkCoordinates::new(), 2);
+        // The stone shows its face through the glass, but the glass hides nothing behind it
+        assert_eq!(area_on_plane(&mesh, 0, 1.0), 1.0);
+        assert_eq!(mesh.indices().len(), (6 + 5) * 2 * 3);
+
+        // Two blocks of stone hide the face between them
+        let world = world_with(&[((0, 0, 0), Block::Stone), ((1, 0, 0), Block::Stone)], 2);
+        let mesh = GreedyMesher::new(&world).build(&ChunkCoordinates::new(), 2);
+        assert_eq!(area_on_plane(&mesh, 0, 1.0), 0.0);
+
+        // So do two blocks of glass
+        let world = world_with(&[((0, 0, 0), Block::Glass), ((1, 0, 0), Block::Glass)], 2);
+        let mesh = GreedyMesher::new(&world).build(&ChunkCoordinates::new(), 2);
+        assert_eq!(area_on_plane(&mesh, 0, 1.0), 0.0);
+        assert_eq!(area_on_plane(&mesh, 0, 2.0), 1.0);
+    }
+
+    #[test]
+    fn test_split_mesh() {
+        let world = world_with(&[((0, 0, 0), Block::Stone), ((1, 0, 0), Block::Glass)], 2);
+        let split = GreedyMesher::new(&world).build_split(&ChunkCoordinates::new(), 2);
+        // All six sides of the stone, including the one behind the glass
+        assert_eq!(split.opaque.indices().len(), 6 * 2 * 3);
+        assert!(split.opaque.vertices().iter().all(|vertex| vertex.x() <= 1.0));
+        assert_eq!(area_on_plane(&split.opaque, 0, 1.0), 1.0);
+        assert_eq!(split.transparent.indices().len(), 5 * 2 * 3);
+        assert!(split.transparent.vertices().iter().all(|vertex| vertex.x() >= 1.0));
+        assert_eq!(area_on_plane(&split.transparent, 0, 1.0), 0.0);
+
+        let mesh = split.merge();
+        assert_eq!(mesh.indices().len(), (6 + 5) * 2 * 3);
+        assert!(mesh.indices().iter().all(|i| (*i as usize) < mesh.vertices().len()));
+    }
+}
@@ -1,35 +1,123 @@
-use super::{Mesher, Mesh};
+use super::{Mesher, Mesh, MesherConfig, NormalMode, SplitMesh, Winding};
 use crate::world::{ChunkCoordinates, World};
-use crate::grid::Grid;
+use crate::chunk::Chunk;
+use crate::grid::{Grid, GridPool};
+use std::sync::Mutex;
 use crate::VoxelData;
-use crate::direction::{Edge, DirectionMapper};
 use glam as math;
 
 pub struct MarchingCubesMesher<'a, T> {
-    world: &'a World<T>
+    world: &'a World<T>,
+    mesher: MarchingCubesChunkMesher<T>,
 }
 
-impl<'a, T: VoxelData> Mesher<'a, T> for MarchingCubesMesher<'a, T> {
-    fn new(world: &'a World<T>) -> Self {
+impl<'a, T> MarchingCubesMesher<'a, T> {
+    pub fn with_config(world: &'a World<T>, config: MesherConfig) -> Self {
         MarchingCubesMesher {
-            world
+            world,
+            mesher: MarchingCubesChunkMesher::new(config),
+        }
+    }
+    pub fn config(&self) -> &MesherConfig {
+        self.mesher.config()
+    }
+}
+
+/// Marching cubes over chunks given directly rather than by their location in a world.
+/// It doesn't borrow any world, so it can mesh a snapshot such as an `Arc<Chunk<T>>`
+/// while the world it was taken from keeps being edited.
+pub struct MarchingCubesChunkMesher<T> {
+    config: MesherConfig,
+    // Only locked to take and return a grid, so threads sharing the mesher mesh in parallel
+    pool: Mutex<GridPool<T>>,
+}
+
+impl<T> MarchingCubesChunkMesher<T> {
+    pub fn new(config: MesherConfig) -> Self {
+        MarchingCubesChunkMesher {
+            config,
+            pool: Mutex::new(GridPool::new()),
         }
     }
+    pub fn config(&self) -> &MesherConfig {
+        &self.config
+    }
+}
+
+impl<'a, T: VoxelData> Mesher<'a, T> for MarchingCubesMesher<'a, T> {
+    fn new(world: &'a World<T>) -> Self {
+        Self::with_config(world, Default::default())
+    }
 
     fn build(&self, chunk_location: &ChunkCoordinates, lod: u8) -> Mesh {
+        self.build_capped(chunk_location, lod, usize::MAX).0
+    }
+
+    fn build_split(&self, chunk_location: &ChunkCoordinates, lod: u8) -> SplitMesh {
+        self.build_split_capped(chunk_location, lod, usize::MAX).0
+    }
+}
+
+/// Density gradient at a grid cell with central differences,
+/// falling back to one sided differences on the border of the grid.
+fn density_gradient<T: VoxelData>(grid: &Grid<T>, cell: (usize, usize, usize)) -> math::Vec3 {
+    let max = grid.get_size() - 1;
+    let difference = |lower: (usize, usize, usize), upper: (usize, usize, usize), width: usize| {
+        (grid[upper].density() - grid[lower].density()) / width as f32
+    };
+    let axis = |value: usize| (value.saturating_sub(1), (value + 1).min(max));
+    let (x0, x1) = axis(cell.0);
+    let (y0, y1) = axis(cell.1);
+    let (z0, z1) = axis(cell.2);
+    math::Vec3::new(
+        difference((x0, cell.1, cell.2), (x1, cell.1, cell.2), x1 - x0),
+        difference((cell.0, y0, cell.2), (cell.0, y1, cell.2), y1 - y0),
+        difference((cell.0, cell.1, z0), (cell.0, cell.1, z1), z1 - z0),
+    )
+}
+
+impl<'a, T: VoxelData> MarchingCubesMesher<'a, T> {
+    /// Same as `build`, but stops before the mesh exceeds `max_triangles`.
+    /// The returned flag is set when the mesh was truncated.
+    /// Cells are meshed in scan order, x varying slowest and z fastest, so a truncated mesh
+    /// covers the cells with the lowest x rather than the coarsest features of the chunk.
+    pub fn build_capped(&self, chunk_location: &ChunkCoordinates, lod: u8, max_triangles: usize) -> (Mesh, bool) {
+        let (split, truncated) = self.build_split_capped(chunk_location, lod, max_triangles);
+        (split.merge(), truncated)
+    }
+
+    /// Same as `build_capped`, with the triangles split by opacity.
+    pub fn build_split_capped(&self, chunk_location: &ChunkCoordinates, lod: u8, max_triangles: usize) -> (SplitMesh, bool) {
         let chunk = self.world.get_chunk_ref(chunk_location)
-            .expect(&format!("Trying to build a chunk that doesn't exist at {:?}", chunk_location));
+            .unwrap_or_else(|| panic!("Trying to build a chunk that doesn't exist at {:?}", chunk_location));
+        self.mesher.build_chunk_split_capped(chunk, lod, max_triangles)
+    }
+}
 
-        let mut mesh = Mesh {
-            vertices: vec![],
-            indices: vec![]
-        };
+impl<T: VoxelData> MarchingCubesChunkMesher<T> {
+    pub fn build_chunk(&self, chunk: &Chunk<T>, lod: u8) -> Mesh {
+        self.build_chunk_split(chunk, lod).merge()
+    }
+
+    /// Same as `build_chunk`, with the triangles split by opacity.
+    pub fn build_chunk_split(&self, chunk: &Chunk<T>, lod: u8) -> SplitMesh {
+        self.build_chunk_split_capped(chunk, lod, usize::MAX).0
+    }
 
-        let mut count: u32 = 0;
+    /// Same as `build_chunk_split`, but stops before the mesh exceeds `max_triangles`.
+    /// The returned flag is set when the mesh was truncated, in scan order like `build_capped`.
+    pub fn build_chunk_split_capped(&self, chunk: &Chunk<T>, lod: u8, max_triangles: usize) -> (SplitMesh, bool) {
+        let mut split = SplitMesh::default();
+        let mut num_triangles_built = 0;
+        let mut truncated = false;
 
-        let grid = Grid::new(&chunk, lod);
+        let grid = self.pool.lock().unwrap().get(chunk, lod);
 
         for (position, cell) in grid.iter_grouped() {
+            // The two opposite corners being interior covers all eight corners of the cell
+            if grid.is_interior(position) && grid.is_interior((position.0 + 1, position.1 + 1, position.2 + 1)) {
+                continue;
+            }
             let mut edge_index: u8 = 0;
             for node in cell.iter().rev() {
                 edge_index <<= 1;
@@ -38,23 +126,22 @@ impl<'a, T: VoxelData> Mesher<'a, T> for MarchingCubesMesher<'a, T> {
                 }
             }
 
-            let edge_bin = super::MC_TABLE[edge_index as usize];
-
-            for edges in edge_bin.iter() {
-                let edges = *edges;
-                if edges == std::u16::MAX {
-                    // Marks the end of array
-                    break;
-                }
-
-                // Each element here represents an edge to connect.
-                debug_assert_eq!(edges >> 12, 0); // Highest 4 bits are always 0
-                let edge1: Edge = ((edges & 0b1111) as u8).into();
-                let edge2: Edge = (((edges >> 4) & 0b1111) as u8).into();
-                let edge3: Edge = ((edges >> 8) as u8).into();
-
+            let num_triangles = super::mc_table::triangles(edge_index).count();
+            if num_triangles_built + num_triangles > max_triangles {
+                truncated = true;
+                break;
+            }
+            num_triangles_built += num_triangles;
+            for edges in super::mc_table::triangles(edge_index) {
+                // Each edge crosses the surface, with one solid end
+                let opaque = edges.iter().all(|edge| {
+                    let (v1, v2) = edge.vertices();
+                    let solid = if cell[v1].is_empty() { cell[v2] } else { cell[v1] };
+                    solid.is_opaque()
+                });
+                let mesh = if opaque { &mut split.opaque } else { &mut split.transparent };
+                let count = mesh.vertices.len() as u32;
                 // We need to connect the midpoints of these three edges
-                let edges = [edge1, edge2, edge3];
                 for edge in &edges {
                     let (v1, v2) = edge.vertices();
                     let v1 = v1.breakdown();
@@ -64,14 +151,246 @@ impl<'a, T: VoxelData> Mesher<'a, T> for MarchingCubesMesher<'a, T> {
                         (v1.1 + v2.1) as f32,
                         (v1.2 + v2.2) as f32,
                     ) / 2.0;
-                    mesh.vertices.push(midpoint);
+                    let cell = math::Vec3::new(position.0 as f32, position.1 as f32, position.2 as f32)
+                        + math::Vec3::splat(self.config.sample_mode.offset());
+                    let mut vertex = (cell + midpoint) * self.config.scale;
+                    if let Some(step) = self.config.quantize {
+                        vertex = (vertex / step).round() * step;
+                    }
+                    mesh.vertices.push(vertex);
+                    if self.config.normals == NormalMode::Gradient {
+                        let corner = |v: (u8, u8, u8)| (position.0 + v.0 as usize, position.1 + v.1 as usize, position.2 + v.2 as usize);
+                        let gradient = density_gradient(&grid, corner(v1)) + density_gradient(&grid, corner(v2));
+                        let normal = if gradient.length_squared() > 0.0 {
+                            -gradient.normalize()
+                        } else {
+                            math::Vec3::zero()
+                        };
+                        mesh.normals.push(normal);
+                    }
                 }
                 mesh.indices.push(count);
-                mesh.indices.push(count + 1);
-                mesh.indices.push(count + 2);
-                count += 3;
+                match self.config.winding {
+                    Winding::CounterClockwise => {
+                        mesh.indices.push(count + 1);
+                        mesh.indices.push(count + 2);
+                    }
+                    Winding::Clockwise => {
+                        mesh.indices.push(count + 2);
+                        mesh.indices.push(count + 1);
+                    }
+                }
             }
         }
-        mesh
+        self.pool.lock().unwrap().recycle(grid);
+        (split, truncated)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::index_path::IndexPath;
+    use crate::direction::Direction;
+
+    #[derive(Copy, Clone, Default, PartialEq, Debug)]
+    struct Solid(bool);
+    impl VoxelData for Solid {
+        fn is_empty(&self) -> bool {
+            !self.0
+        }
+    }
+
+    fn single_octant_world() -> World<Solid> {
+        let mut chunk: Chunk<Solid> = Chunk::new();
+        chunk.set(IndexPath::new().push(Direction::FrontLeftBottom), Solid(true));
+        let mut world = World::new();
+        world.insert_chunk(ChunkCoordinates::new(), chunk);
+        world
+    }
+
+    #[test]
+    fn test_config() {
+        let world = single_octant_world();
+        let default_mesh = MarchingCubesMesher::new(&world).build(&ChunkCoordinates::new(), 1);
+        assert!(!default_mesh.indices.is_empty());
+
+        let config = MesherConfig::new()
+            .scale(2.0)
+            .winding(Winding::Clockwise);
+        let mesher = MarchingCubesMesher::with_config(&world, config);
+        assert_eq!(mesher.config().scale, 2.0);
+        let mesh = mesher.build(&ChunkCoordinates::new(), 1);
+
+        assert_eq!(mesh.vertices.len(), default_mesh.vertices.len());
+        for (scaled, original) in mesh.vertices.iter().zip(default_mesh.vertices.iter()) {
+            assert_eq!(*scaled, *original * 2.0);
+        }
+        for (triangle, original) in mesh.indices.chunks(3).zip(default_mesh.indices.chunks(3)) {
+            assert_eq!(triangle, &[original[0], original[2], original[1]]);
+        }
+    }
+
+    #[test]
+    fn test_shared_across_threads() {
+        let world = single_octant_world();
+        let mesher = MarchingCubesMesher::new(&world);
+        let expected = mesher.build(&ChunkCoordinates::new(), 2);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| mesher.build(&ChunkCoordinates::new(), 2)))
+                .collect();
+            for handle in handles {
+                let mesh = handle.join().unwrap();
+                assert_eq!(mesh.vertices(), expected.vertices());
+                assert_eq!(mesh.indices(), expected.indices());
+            }
+        });
+    }
+
+    #[test]
+    fn test_standalone() {
+        use std::sync::Arc;
+
+        let mut world = single_octant_world();
+        let expected = MarchingCubesMesher::new(&world).build(&ChunkCoordinates::new(), 2);
+        let snapshot = Arc::new(world.get_chunk_ref(&ChunkCoordinates::new()).unwrap().clone());
+        let mesher = MarchingCubesChunkMesher::new(Default::default());
+
+        // The world keeps changing while the snapshot is meshed
+        world.insert_chunk(ChunkCoordinates::new(), Chunk::new());
+        let mesh = mesher.build_chunk(&snapshot, 2);
+        assert!(!mesh.vertices().is_empty());
+        assert_eq!(mesh.vertices(), expected.vertices());
+        assert_eq!(mesh.indices(), expected.indices());
+
+        let mesh = MarchingCubesMesher::new(&world).build(&ChunkCoordinates::new(), 2);
+        assert!(mesh.vertices().is_empty());
+    }
+
+    #[test]
+    fn test_gradient_normals() {
+        use crate::density::DensityVoxel;
+
+        // A ball in the middle of the grid, with the density falling off from the center
+        let center = math::Vec3::splat(7.5);
+        let mut grid = Grid::filled(4, DensityVoxel::default());
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    let distance = (math::Vec3::new(x as f32, y as f32, z as f32) - center).length();
+                    let density = ((5.0 - distance) * 40.0).clamp(-127.0, 127.0);
+                    grid[(x, y, z)] = DensityVoxel::new(density as i8, 0);
+                }
+            }
+        }
+        let mut world = World::new();
+        world.insert_chunk(ChunkCoordinates::new(), Chunk::from_grid(&grid));
+
+        let mesh = MarchingCubesMesher::new(&world).build(&ChunkCoordinates::new(), 4);
+        assert!(mesh.normals().is_empty());
+
+        let config = MesherConfig::new().normals(NormalMode::Gradient);
+        let mesh = MarchingCubesMesher::with_config(&world, config).build(&ChunkCoordinates::new(), 4);
+        assert!(!mesh.vertices().is_empty());
+        assert_eq!(mesh.normals().len(), mesh.vertices().len());
+        for (vertex, normal) in mesh.vertices().iter().zip(mesh.normals()) {
+            let radial = (*vertex - center).normalize();
+            assert!((normal.length() - 1.0).abs() < 1e-4);
+            assert!(normal.dot(radial) > 0.8, "{:?} at {:?}", normal, vertex);
+        }
+    }
+
+    #[test]
+    fn test_quantize() {
+        let world = single_octant_world();
+        let config = MesherConfig::new()
+            .scale(0.1)
+            .quantize(1.0 / 256.0);
+        let mesher = MarchingCubesMesher::with_config(&world, config);
+        let mesh = mesher.build(&ChunkCoordinates::new(), 2);
+        assert!(!mesh.vertices().is_empty());
+        for vertex in mesh.vertices() {
+            for &coord in &[vertex.x(), vertex.y(), vertex.z()] {
+                let steps = coord * 256.0;
+                assert_eq!(steps, steps.round());
+            }
+        }
+
+        let again = mesher.build(&ChunkCoordinates::new(), 2);
+        let bits = |mesh: &Mesh| -> Vec<[u32; 3]> {
+            mesh.vertices().iter().map(|v| [v.x().to_bits(), v.y().to_bits(), v.z().to_bits()]).collect()
+        };
+        assert_eq!(bits(&mesh), bits(&again));
+        assert_eq!(mesh.indices(), again.indices());
+    }
+
+    #[test]
+    fn test_sample_mode() {
+        use crate::mesher::SampleMode;
+
+        let world = single_octant_world();
+        let corner = MarchingCubesMesher::new(&world).build(&ChunkCoordinates::new(), 2);
+        let config = MesherConfig::new()
+            .scale(2.0)
+            .sample_mode(SampleMode::Center);
+        let center = MarchingCubesMesher::with_config(&world, config).build(&ChunkCoordinates::new(), 2);
+
+        assert!(!corner.vertices().is_empty());
+        assert_eq!(corner.vertices().len(), center.vertices().len());
+        assert_eq!(corner.indices(), center.indices());
+        for (shifted, original) in center.vertices().iter().zip(corner.vertices().iter()) {
+            assert_eq!(*shifted, (*original + math::Vec3::splat(0.5)) * 2.0);
+        }
+    }
+
+    #[test]
+    fn test_build_split() {
+        // An opaque voxel near the bottom and a see-through one near the top
+        #[derive(Copy, Clone, Default, PartialEq, Debug)]
+        struct Block(u8);
+        impl VoxelData for Block {
+            fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+            fn is_opaque(&self) -> bool {
+                self.0 == 1
+            }
+        }
+        let mut chunk: Chunk<Block> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 1, 0), 3), Block(1));
+        chunk.set(IndexPath::from_cell((5, 5, 6), 3), Block(2));
+        let mut world = World::new();
+        world.insert_chunk(ChunkCoordinates::new(), chunk);
+
+        let mesher = MarchingCubesMesher::new(&world);
+        let split = mesher.build_split(&ChunkCoordinates::new(), 3);
+        assert!(!split.opaque.indices().is_empty());
+        assert!(!split.transparent.indices().is_empty());
+        assert!(split.opaque.vertices().iter().all(|vertex| vertex.z() < 4.0));
+        assert!(split.transparent.vertices().iter().all(|vertex| vertex.z() > 4.0));
+
+        let full = mesher.build(&ChunkCoordinates::new(), 3);
+        assert_eq!(full.indices().len(), split.opaque.indices().len() + split.transparent.indices().len());
+    }
+
+    #[test]
+    fn test_build_capped() {
+        let world = single_octant_world();
+        let mesher = MarchingCubesMesher::new(&world);
+        let full = mesher.build(&ChunkCoordinates::new(), 2);
+        let total = full.indices.len() / 3;
+        assert!(total > 2);
+
+        let (mesh, truncated) = mesher.build_capped(&ChunkCoordinates::new(), 2, 2);
+        assert!(truncated);
+        assert!(mesh.indices.len() / 3 <= 2);
+        assert_eq!(mesh.vertices.len(), mesh.indices.len());
+
+        let (mesh, truncated) = mesher.build_capped(&ChunkCoordinates::new(), 2, total);
+        assert!(!truncated);
+        assert_eq!(mesh.indices.len(), full.indices.len());
     }
 }
@@ -0,0 +1,111 @@
+use crate::chunk::Chunk;
+use crate::index_path::IndexPath;
+use crate::VoxelData;
+use glam as math;
+
+//   Square Corners & Edges
+//
+//   2--------2--------3
+//   |                 |
+//   3                 1
+//   |                 |
+//   0--------0--------1
+//
+// Corners follow the bit layout of `Direction` on the x/y plane.
+const SQUARE_EDGES: [(u8, u8); 4] = [(0, 1), (1, 3), (2, 3), (0, 2)];
+
+impl<T: VoxelData, M> Chunk<T, M> {
+    /// Contour line segments of the solid/empty boundary on the slice `z_index`
+    /// of a grid subdivided `z_depth` times. Samples are taken at cell centers,
+    /// and everything outside the chunk is considered empty so contours are closed.
+    pub fn marching_squares(&self, z_depth: u8, z_index: u32) -> Vec<(math::Vec2, math::Vec2)> {
+        let size: i64 = 1 << z_depth;
+        let is_solid = |x: i64, y: i64| -> bool {
+            if x < 0 || y < 0 || x >= size || y >= size {
+                return false;
+            }
+            let index_path = IndexPath::from_cell((x as u32, y as u32, z_index), z_depth);
+            !self.get(index_path).is_empty()
+        };
+
+        let mut segments = Vec::new();
+        for x in -1..size {
+            for y in -1..size {
+                let mut corners = [false; 4];
+                for (i, corner) in corners.iter_mut().enumerate() {
+                    *corner = is_solid(x + (i & 1) as i64, y + (i >> 1) as i64);
+                }
+                let edge_point = |edge: usize| -> math::Vec2 {
+                    let (v1, v2) = SQUARE_EDGES[edge];
+                    let midpoint = math::Vec2::new(
+                        (x * 2 + (v1 & 1) as i64 + (v2 & 1) as i64) as f32 / 2.0,
+                        (y * 2 + (v1 >> 1) as i64 + (v2 >> 1) as i64) as f32 / 2.0,
+                    );
+                    // Shift from cell-center samples into chunk space
+                    (midpoint + math::Vec2::splat(0.5)) / size as f32
+                };
+
+                let crossed: Vec<usize> = (0..4)
+                    .filter(|&edge| {
+                        let (v1, v2) = SQUARE_EDGES[edge];
+                        corners[v1 as usize] != corners[v2 as usize]
+                    })
+                    .collect();
+                match crossed.len() {
+                    0 => (),
+                    2 => segments.push((edge_point(crossed[0]), edge_point(crossed[1]))),
+                    _ => {
+                        // Saddle: separate the two solid corners from each other.
+                        for (corner, _) in corners.iter().enumerate().filter(|(_, solid)| **solid) {
+                            let mut edges = (0..4).filter(|&edge| {
+                                let (v1, v2) = SQUARE_EDGES[edge];
+                                v1 as usize == corner || v2 as usize == corner
+                            });
+                            let e1 = edges.next().unwrap();
+                            let e2 = edges.next().unwrap();
+                            segments.push((edge_point(e1), edge_point(e2)));
+                        }
+                    }
+                }
+            }
+        }
+        segments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Copy, Clone, Default, PartialEq, Debug)]
+    struct Solid(bool);
+    impl VoxelData for Solid {
+        fn is_empty(&self) -> bool {
+            !self.0
+        }
+    }
+
+    #[test]
+    fn test_closed_loop() {
+        let mut chunk: Chunk<Solid> = Chunk::new();
+        for &(x, y) in &[(1, 1), (1, 2), (2, 1), (2, 2)] {
+            chunk.set(IndexPath::from_cell((x, y, 1), 2), Solid(true));
+        }
+        assert!(chunk.marching_squares(2, 0).is_empty());
+
+        let segments = chunk.marching_squares(2, 1);
+        assert_eq!(segments.len(), 8);
+
+        // Every endpoint is shared by exactly two segments
+        let mut degrees: HashMap<(u32, u32), usize> = HashMap::new();
+        for (a, b) in &segments {
+            for point in &[a, b] {
+                assert!(point.x() > 0.0 && point.x() < 1.0);
+                assert!(point.y() > 0.0 && point.y() < 1.0);
+                *degrees.entry((point.x().to_bits(), point.y().to_bits())).or_insert(0) += 1;
+            }
+        }
+        assert!(degrees.values().all(|degree| *degree == 2));
+    }
+}
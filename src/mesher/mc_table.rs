@@ -1,3 +1,7 @@
+use crate::direction::Edge;
+
+/// Each case lists up to five triangles. A triangle connects the midpoints of three edges,
+/// packed as 4 bits per edge. `0xffff` marks the end of the list.
 pub const MC_TABLE: [[u16; 5]; 256] = [
     [0xffff, 0xffff, 0xffff, 0xffff, 0xffff],
     [0x02b3, 0xffff, 0xffff, 0xffff, 0xffff],
@@ -256,3 +260,40 @@ pub const MC_TABLE: [[u16; 5]; 256] = [
     [0x0b23, 0xffff, 0xffff, 0xffff, 0xffff],
     [0xffff, 0xffff, 0xffff, 0xffff, 0xffff],
 ];
+
+/// Unpack a triangle entry of `MC_TABLE` into its three edges.
+pub fn decode_triangle(edges: u16) -> [Edge; 3] {
+    debug_assert_eq!(edges >> 12, 0); // Highest 4 bits are always 0
+    [
+        ((edges & 0b1111) as u8).into(),
+        (((edges >> 4) & 0b1111) as u8).into(),
+        ((edges >> 8) as u8).into(),
+    ]
+}
+
+/// Triangles of the configuration, where bit n of `case` is set when corner n is solid.
+pub fn triangles(case: u8) -> impl Iterator<Item = [Edge; 3]> {
+    MC_TABLE[case as usize].iter()
+        .take_while(|edges| **edges != u16::MAX)
+        .map(|edges| decode_triangle(*edges))
+}
+
+/// Iterates all 256 marching cubes configurations along with their triangles.
+pub fn cases() -> impl Iterator<Item = (u8, Vec<[Edge; 3]>)> {
+    (0..=255_u8).map(|case| (case, triangles(case).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cases() {
+        let cases: Vec<_> = cases().collect();
+        assert_eq!(cases.len(), 256);
+        assert!(cases[0].1.is_empty());
+        assert!(cases[255].1.is_empty());
+        assert_eq!(cases[1].1.len(), 1);
+        assert!(cases[1..255].iter().all(|(_, triangles)| !triangles.is_empty()));
+    }
+}
@@ -1,16 +1,98 @@
 mod marching_cubes;
-mod mc_table;
+pub mod mc_table;
+mod marching_squares;
+mod config;
+mod adjacency;
+mod registry;
+mod greedy;
 use super::world::{World, ChunkCoordinates};
 use super::chunk::Chunk;
 use glam as math;
 pub use mc_table::MC_TABLE;
+pub use config::{MesherConfig, NormalMode, SampleMode, Winding};
+pub use adjacency::MeshAdjacency;
+pub use marching_cubes::{MarchingCubesMesher, MarchingCubesChunkMesher};
+pub use greedy::GreedyMesher;
+pub use registry::{MeshStyle, MesherRegistry};
 
+#[derive(Default)]
 pub struct Mesh {
     vertices: Vec<math::Vec3>,
     indices: Vec<u32>,
+    normals: Vec<math::Vec3>,
+}
+
+impl Mesh {
+    pub fn vertices(&self) -> &[math::Vec3] {
+        &self.vertices
+    }
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+    /// One normal for each vertex, or empty when the mesher was configured without normals.
+    pub fn normals(&self) -> &[math::Vec3] {
+        &self.normals
+    }
+    /// Add the triangles of `other` after the ones of this mesh.
+    /// Combining meshes built in parallel in a stable order gives the same result on every run.
+    pub fn append(&mut self, other: Mesh) {
+        let base = self.vertices.len() as u32;
+        self.vertices.extend(other.vertices);
+        self.normals.extend(other.normals);
+        self.indices.extend(other.indices.into_iter().map(|index| index + base));
+    }
+}
+
+/// A mesh split by the opacity of the voxels it came from,
+/// for renderers drawing opaque and transparent geometry in separate passes.
+#[derive(Default)]
+pub struct SplitMesh {
+    pub opaque: Mesh,
+    pub transparent: Mesh,
+}
+
+impl SplitMesh {
+    /// Both meshes in one, with the transparent triangles after the opaque ones.
+    pub fn merge(self) -> Mesh {
+        let mut mesh = self.opaque;
+        mesh.append(self.transparent);
+        mesh
+    }
 }
 
 pub trait Mesher<'a, T> {
-    fn new(world: &'a World<T>) -> Self;
+    fn new(world: &'a World<T>) -> Self where Self: Sized;
     fn build(&self, chunk_location: &ChunkCoordinates, lod: u8) -> Mesh;
+    /// Same as `build`, with the triangles split by `VoxelData::is_opaque`.
+    /// Meshers unaware of transparency put everything in the opaque mesh.
+    fn build_split(&self, chunk_location: &ChunkCoordinates, lod: u8) -> SplitMesh {
+        SplitMesh {
+            opaque: self.build(chunk_location, lod),
+            transparent: Mesh::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append() {
+        let mut mesh = Mesh {
+            vertices: vec![math::Vec3::new(0.0, 0.0, 0.0), math::Vec3::new(1.0, 0.0, 0.0), math::Vec3::new(0.0, 1.0, 0.0)],
+            indices: vec![0, 1, 2],
+            normals: vec![],
+        };
+        let other = Mesh {
+            vertices: vec![math::Vec3::new(0.0, 0.0, 1.0), math::Vec3::new(1.0, 0.0, 1.0), math::Vec3::new(0.0, 1.0, 1.0)],
+            indices: vec![2, 1, 0],
+            normals: vec![],
+        };
+        mesh.append(other);
+        assert_eq!(mesh.vertices().len(), 6);
+        assert_eq!(mesh.indices(), &[0, 1, 2, 5, 4, 3]);
+        assert_eq!(mesh.vertices()[mesh.indices()[3] as usize], math::Vec3::new(0.0, 1.0, 1.0));
+        assert_eq!(mesh.vertices()[mesh.indices()[5] as usize], math::Vec3::new(0.0, 0.0, 1.0));
+    }
 }
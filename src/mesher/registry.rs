@@ -0,0 +1,101 @@
+use super::{Mesher, Mesh};
+use crate::world::ChunkCoordinates;
+use std::collections::HashMap;
+
+/// Which mesher a chunk should be built with.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum MeshStyle {
+    /// Smooth surfaces interpolated from the densities, e.g. marching cubes.
+    #[default]
+    Smooth,
+    /// Blocky surfaces made of axis aligned quads.
+    Greedy,
+}
+
+/// One mesher for each `MeshStyle`, picked for each chunk by `World::build_mesh`.
+pub struct MesherRegistry<'a, T> {
+    meshers: HashMap<MeshStyle, Box<dyn Mesher<'a, T> + 'a>>,
+}
+
+impl<'a, T> Default for MesherRegistry<'a, T> {
+    fn default() -> Self {
+        MesherRegistry {
+            meshers: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, T> MesherRegistry<'a, T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Use `mesher` for the chunks of `style`, replacing the one registered before.
+    pub fn register<M: Mesher<'a, T> + 'a>(&mut self, style: MeshStyle, mesher: M) {
+        self.meshers.insert(style, Box::new(mesher));
+    }
+    pub fn get(&self, style: MeshStyle) -> Option<&(dyn Mesher<'a, T> + 'a)> {
+        self.meshers.get(&style).map(|mesher| mesher.as_ref())
+    }
+    /// Build the chunk with the mesher registered for `style`, or None if there isn't one.
+    pub fn build(&self, style: MeshStyle, chunk_location: &ChunkCoordinates, lod: u8) -> Option<Mesh> {
+        self.get(style).map(|mesher| mesher.build(chunk_location, lod))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::MarchingCubesMesher;
+    use crate::chunk::Chunk;
+    use crate::density::DensityVoxel;
+    use crate::index_path::IndexPath;
+    use crate::world::World;
+    use glam as math;
+
+    // Emits a single marker vertex at the chunk origin
+    struct MarkerMesher<'a>(&'a World<DensityVoxel>);
+    impl<'a> Mesher<'a, DensityVoxel> for MarkerMesher<'a> {
+        fn new(world: &'a World<DensityVoxel>) -> Self {
+            MarkerMesher(world)
+        }
+        fn build(&self, chunk_location: &ChunkCoordinates, _lod: u8) -> Mesh {
+            assert!(self.0.get_chunk_ref(chunk_location).is_some());
+            Mesh {
+                vertices: vec![chunk_location.origin().into()],
+                indices: vec![],
+                normals: vec![],
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_mesh() {
+        let mut world: World<DensityVoxel> = World::new();
+        for x in 0..2 {
+            let mut chunk = Chunk::new();
+            chunk.set(IndexPath::from_cell((0, 0, 0), 1), DensityVoxel::new(1, 0));
+            world.insert_chunk((x, 0, 0).into(), chunk);
+        }
+        world.set_mesh_style(&(1, 0, 0).into(), MeshStyle::Greedy);
+        assert_eq!(world.mesh_style(&(0, 0, 0).into()), MeshStyle::Smooth);
+        assert_eq!(world.mesh_style(&(1, 0, 0).into()), MeshStyle::Greedy);
+
+        // The style is kept on the chunk, so it travels with it
+        let chunk = world.remove_chunk(&(1, 0, 0).into()).unwrap();
+        assert_eq!(chunk.mesh_style(), MeshStyle::Greedy);
+        assert_eq!(world.mesh_style(&(1, 0, 0).into()), MeshStyle::Smooth);
+        world.insert_chunk((1, 0, 0).into(), chunk);
+        assert_eq!(world.mesh_style(&(1, 0, 0).into()), MeshStyle::Greedy);
+
+        let mut meshers = MesherRegistry::new();
+        assert!(world.build_mesh(&meshers, &(1, 0, 0).into(), 1).is_none());
+        meshers.register(MeshStyle::Smooth, MarchingCubesMesher::new(&world));
+        meshers.register(MeshStyle::Greedy, MarkerMesher::new(&world));
+
+        let smooth = world.build_mesh(&meshers, &(0, 0, 0).into(), 1).unwrap();
+        assert!(!smooth.indices().is_empty());
+        let greedy = world.build_mesh(&meshers, &(1, 0, 0).into(), 1).unwrap();
+        assert!(greedy.indices().is_empty());
+        assert_eq!(greedy.vertices(), &[math::Vec3::new(1.0, 0.0, 0.0)]);
+    }
+}
@@ -1,6 +1,9 @@
 use crate::direction::{DirectionMapper, Direction};
 use crate::index_path::IndexPath;
+use std::collections::HashMap;
+use std::hash::Hash;
 
+#[derive(Clone)]
 pub struct Node<T> {
     // A pointer pointing towards 8 child nodes
     pub(crate) children: Box<DirectionMapper<Option<Node<T>>>>,
@@ -8,52 +11,206 @@ pub struct Node<T> {
 }
 
 impl<T> Node<T> {
-    /// Get the data on the specified index path. If
+    /// Get the data on the specified index path. If the index path goes deeper
+    /// than the tree does, the data of the leaf containing it is returned.
     pub fn get(&self, index_path: IndexPath) -> &T {
+        let mut node = self;
+        let mut index_path = index_path;
+        loop {
+            let dir = index_path.peek();
+            index_path = index_path.pop();
+            match &node.children[dir] {
+                Some(child) if !index_path.is_empty() => node = child,
+                // Either the end of the path, or a leaf the path runs past
+                _ => return &node.data[dir],
+            }
+        }
+    }
+    /// The subdivided octants, along with their child node.
+    pub fn children_present(&self) -> impl Iterator<Item = (Direction, &Node<T>)> {
+        self.children.enumerate()
+            .filter_map(|(dir, child)| child.as_ref().map(|child| (dir, child)))
+    }
+    /// Depth of the deepest leaf under this node. The octants of a node without children are at depth 1.
+    pub fn depth(&self) -> u8 {
+        1 + self.children_present()
+            .map(|(_, child)| child.depth())
+            .max()
+            .unwrap_or(0)
+    }
+    /// Whether the tree is subdivided deep enough for the index path to address a slot.
+    pub fn contains(&self, index_path: IndexPath) -> bool {
+        if index_path.is_empty() {
+            return true;
+        }
         let dir = index_path.peek();
         let index_path = index_path.pop();
         if index_path.is_empty() {
-            return &self.data[dir];
+            true
         } else if let Some(child) = &self.children[dir] {
-            return child.get(index_path);
+            child.contains(index_path)
         } else {
-            // Trying to access a child while the node is already a leaf node.
-            return &self.data[dir];
+            false
+        }
+    }
+    /// Number of nodes in the tree, including this one.
+    pub fn count_nodes(&self) -> usize {
+        1 + self.children_present()
+            .map(|(_, child)| child.count_nodes())
+            .sum::<usize>()
+    }
+    /// Number of octants that aren't subdivided any further.
+    pub fn count_leaves(&self) -> usize {
+        self.children.iter()
+            .map(|child| child.as_ref().map_or(1, |child| child.count_leaves()))
+            .sum()
+    }
+    /// Bytes held by the boxed children of every node in the tree.
+    /// The data of a node is stored inline, so it is only counted for the children.
+    pub fn memory_usage(&self) -> usize {
+        self.count_nodes() * std::mem::size_of::<DirectionMapper<Option<Node<T>>>>()
+    }
+    /// A tree with the same structure, holding `f` of every value.
+    /// The values kept on subdivided octants are mapped as well.
+    pub fn map<U, F: Fn(&T) -> U>(&self, f: F) -> Node<U> {
+        self.map_ref(&f)
+    }
+    fn map_ref<U, F: Fn(&T) -> U>(&self, f: &F) -> Node<U> {
+        Node {
+            children: Box::new(self.children.map(|child| child.as_ref().map(|child| child.map_ref(f)))),
+            data: self.data.map(f),
         }
     }
 }
 
-impl<T: Copy + PartialEq> Node<T> {
+impl<T: Hash + Eq> Node<T> {
+    /// The leaf value covering the largest volume under this node.
+    /// Ties go to the value whose first leaf comes first, depth first in direction order,
+    /// so the result is the same on every run.
+    pub fn dominant_material(&self) -> &T {
+        // Volumes in the order the values were first found, with their index in `volumes`
+        let mut volumes: Vec<(&T, f64)> = Vec::new();
+        let mut indices: HashMap<&T, usize> = HashMap::new();
+        self.accumulate_volumes(1.0 / 8.0, &mut volumes, &mut indices);
+        volumes.into_iter()
+            .fold(None, |dominant: Option<(&T, f64)>, (value, volume)| match dominant {
+                Some(dominant) if dominant.1 >= volume => Some(dominant),
+                _ => Some((value, volume)),
+            })
+            .map(|(value, _)| value)
+            .unwrap()
+    }
+
+    fn accumulate_volumes<'a>(&'a self, octant_volume: f64, volumes: &mut Vec<(&'a T, f64)>, indices: &mut HashMap<&'a T, usize>) {
+        for (dir, child) in self.children.enumerate() {
+            match child {
+                Some(child) => child.accumulate_volumes(octant_volume / 8.0, volumes, indices),
+                None => {
+                    let index = *indices.entry(&self.data[dir]).or_insert_with(|| {
+                        volumes.push((&self.data[dir], 0.0));
+                        volumes.len() - 1
+                    });
+                    volumes[index].1 += octant_volume;
+                }
+            }
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> Node<T> {
     pub fn new_all(item: T) -> Node<T> {
         Node {
-            children: Box::new(DirectionMapper::new([None; 8])),
-            data: DirectionMapper::new([item; 8])
+            children: Box::new(Direction::map(|_| None)),
+            data: Direction::map(|_| item.clone()),
         }
     }
     /// Set location on the index path to data.
     /// If the index path goes deeper than the tree does, new subnodes will be created as needed.
     pub fn set(&mut self, index_path: IndexPath, data: T) {
+        self.set_with(index_path, data, true)
+    }
+    /// Same as `set`, but leaves uniform nodes on the path subdivided.
+    /// Call `canonicalize` once done to merge them.
+    pub(crate) fn set_unmerged(&mut self, index_path: IndexPath, data: T) {
+        self.set_with(index_path, data, false)
+    }
+    fn set_with(&mut self, index_path: IndexPath, data: T, merge: bool) {
         let dir = index_path.peek();
         let index_path = index_path.pop();
         if index_path.is_empty() {
             self.data[dir] = data;
             return;
         } else if let Some(child) = &mut self.children[dir] {
-            child.set(index_path, data);
+            child.set_with(index_path, data, merge);
         } else {
             // Trying to access a child while the node is already a leaf node.
-            let mut child = Node::<T>::new_all(self.data[dir]);
-            child.set(index_path, data);
+            let mut child = Node::<T>::new_all(self.data[dir].clone());
+            child.set_with(index_path, data, merge);
             self.children[dir] = Some(child);
         }
-
-        let child = self.children[dir].as_ref().unwrap();
-        if child.data.data.windows(2).all(|w| w[0] == w[1]) {
+        if merge {
+            self.merge_child(dir);
+        }
+    }
+    /// Mutable reference to the data on the index path, subdividing as needed like `set` does.
+    /// Unlike `set`, uniform nodes are not merged afterwards, since the value is only known
+    /// once the reference is dropped. Call `canonicalize` once done to merge them.
+    pub fn get_mut(&mut self, index_path: IndexPath) -> &mut T {
+        let dir = index_path.peek();
+        let index_path = index_path.pop();
+        if index_path.is_empty() {
+            return &mut self.data[dir];
+        }
+        if self.children[dir].is_none() {
+            self.children[dir] = Some(Node::new_all(self.data[dir].clone()));
+        }
+        self.children[dir].as_mut().unwrap().get_mut(index_path)
+    }
+    /// Merge the child on `dir` into this node when all its octants are leaves of the same value.
+    fn merge_child(&mut self, dir: Direction) {
+        let mergeable = match &self.children[dir] {
+            Some(child) => child.children.iter().all(|c| c.is_none()) && child.data.data.windows(2).all(|w| w[0] == w[1]),
+            None => false,
+        };
+        if mergeable {
             // Merge child cell
-            self.data[dir] = child.data.data[0]; // TODO: better merging strategy
-            self.children[dir] = None;
+            let child = self.children[dir].take().unwrap();
+            self.data[dir] = child.data.data[0].clone(); // TODO: better merging strategy
         }
     }
+    /// Merge all uniform subtrees, bottom up.
+    pub fn canonicalize(&mut self) {
+        for dir in (0..8_u8).map(Direction::from) {
+            if let Some(child) = &mut self.children[dir] {
+                child.canonicalize();
+                self.merge_child(dir);
+            }
+        }
+    }
+}
+
+impl<T: PartialEq> Node<T> {
+    /// Whether all leaves under this node hold `value`.
+    fn is_uniform_with(&self, value: &T) -> bool {
+        self.children.enumerate().all(|(dir, child)| match child {
+            Some(child) => child.is_uniform_with(value),
+            None => self.data[dir] == *value,
+        })
+    }
+}
+
+/// Two nodes are equal when they describe the same field, even if one of them keeps
+/// a uniform subtree that the other merged. The values kept on subdivided octants are ignored.
+impl<T: PartialEq> PartialEq for Node<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.children.enumerate().all(|(dir, child)| match (child, &other.children[dir]) {
+            (Some(child), Some(other_child)) => child == other_child,
+            (None, None) => self.data[dir] == other.data[dir],
+            // A leaf stands for eight copies of its value
+            (Some(child), None) => child.is_uniform_with(&other.data[dir]),
+            (None, Some(other_child)) => other_child.is_uniform_with(&self.data[dir]),
+        })
+    }
 }
 
 impl<T: std::fmt::Debug> Node<T> {
@@ -97,7 +254,7 @@ impl<T: std::fmt::Debug> std::fmt::Debug for Node<T> {
 #[cfg(test)]
 mod tests {
     use super::Node;
-    use crate::direction::Direction;
+    use crate::direction::{Direction, DirectionMapper};
     use crate::index_path::IndexPath;
 
     #[test]
@@ -124,4 +281,222 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_get_deep() {
+        let mut node: Node<u16> = Node::new_all(0);
+        let index_path = (0..21).fold(IndexPath::new(), |path, i| path.put(Direction::from(i % 8)));
+        assert_eq!(index_path.len(), 21);
+        node.set(index_path, 5);
+        assert_eq!(node.depth(), 21);
+        assert_eq!(*node.get(index_path), 5);
+
+        // Siblings of the deepest slot, and a path running past a leaf
+        let sibling = index_path.del().put(Direction::from(7));
+        assert_eq!(*node.get(sibling), 0);
+        let shallow = IndexPath::new().put(Direction::from(3)).put(Direction::from(1));
+        assert_eq!(*node.get(shallow), 0);
+        node.set(IndexPath::new().put(Direction::from(3)), 2);
+        assert_eq!(*node.get(shallow), 2);
+    }
+
+    #[test]
+    fn test_set_keeps_deeper_nodes() {
+        let mut node: Node<u16> = Node::new_all(0);
+        let index_path = IndexPath::new()
+            .push(Direction::FrontRightBottom)
+            .push(Direction::RearLeftTop)
+            .push(Direction::FrontLeftTop);
+        node.set(index_path, 1);
+        assert_eq!(*node.get(index_path), 1);
+        node.set(index_path, 0);
+        assert!(node.children.iter().all(|c| c.is_none()));
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut node: Node<u16> = Node::new_all(2);
+        let index_path = IndexPath::new().push(Direction::FrontRightBottom).push(Direction::RearLeftTop);
+        *node.get_mut(index_path) += 3;
+        *node.get_mut(index_path) *= 2;
+        assert_eq!(*node.get(index_path), 10);
+        assert_eq!(*node.get(IndexPath::new().push(Direction::FrontRightBottom).push(Direction::RearRightTop)), 2);
+
+        // Writing back the same value leaves the subdivision in place until canonicalized
+        *node.get_mut(index_path) = 2;
+        assert_eq!(node.depth(), 2);
+        node.canonicalize();
+        assert_eq!(node.depth(), 1);
+        assert_eq!(*node.get(index_path), 2);
+    }
+
+    #[test]
+    fn test_map() {
+        let mut node: Node<u16> = Node::new_all(0);
+        let deep = IndexPath::new().put(Direction::FrontRightBottom).put(Direction::RearLeftTop).put(Direction::FrontLeftTop);
+        let shallow = IndexPath::new().put(Direction::RearRightTop);
+        node.set(deep, 7);
+        node.set(shallow, 3);
+        let mapped: Node<String> = node.map(|value| format!("#{}", value));
+        assert_eq!(mapped.get(deep), "#7");
+        assert_eq!(mapped.get(shallow), "#3");
+        assert_eq!(mapped.get(IndexPath::new().put(Direction::FrontRightBottom).put(Direction::RearLeftTop).put(Direction::RearLeftTop)), "#0");
+        assert_eq!(mapped.depth(), node.depth());
+        assert_eq!(mapped.children_present().count(), node.children_present().count());
+        assert!(mapped.children[Direction::FrontRightBottom].as_ref().unwrap().children[Direction::RearLeftTop].is_some());
+    }
+
+    #[test]
+    fn test_count_nodes() {
+        let mut node: Node<u16> = Node::new_all(0);
+        assert_eq!(node.count_nodes(), 1);
+        assert_eq!(node.count_leaves(), 8);
+        let box_size = std::mem::size_of::<DirectionMapper<Option<Node<u16>>>>();
+        assert_eq!(node.memory_usage(), box_size);
+
+        let index_path = IndexPath::new().put(Direction::FrontRightBottom).put(Direction::RearLeftTop).put(Direction::FrontLeftTop);
+        node.set(index_path, 1);
+        assert_eq!(node.count_nodes(), 3);
+        assert_eq!(node.count_leaves(), 8 + 7 + 7);
+        assert_eq!(node.memory_usage(), 3 * box_size);
+
+        // Filling the rest of the deepest node merges it back
+        for dir in (0..8).map(Direction::from) {
+            node.set(IndexPath::new().put(Direction::FrontRightBottom).put(Direction::RearLeftTop).put(dir), 1);
+        }
+        assert_eq!(node.count_nodes(), 2);
+        assert_eq!(node.count_leaves(), 8 + 7);
+    }
+
+    #[test]
+    fn test_dominant_material() {
+        // 75% of the volume is 1, the rest is split between 2 and 3
+        let mut node: Node<u16> = Node::new_all(1);
+        node.set(IndexPath::new().push(Direction::FrontLeftBottom), 2);
+        node.set(IndexPath::new().push(Direction::RearRightTop), 3);
+        for i in 0..4_u8 {
+            node.set(IndexPath::new().push(Direction::RearRightTop).push(i.into()), 2);
+        }
+        assert_eq!(*node.dominant_material(), 1);
+
+        // Ties go to the value found first
+        let mut node: Node<u16> = Node::new_all(1);
+        for i in 4..8_u8 {
+            node.set(IndexPath::new().push(i.into()), 2);
+        }
+        assert_eq!(*node.dominant_material(), 1);
+        let mut node: Node<u16> = Node::new_all(2);
+        for i in 4..8_u8 {
+            node.set(IndexPath::new().push(i.into()), 1);
+        }
+        assert_eq!(*node.dominant_material(), 2);
+
+        let mut node: Node<u16> = Node::new_all(1);
+        node.set(IndexPath::new().push(Direction::RearLeftTop), 2);
+        node.set(IndexPath::new().push(Direction::FrontRightTop), 2);
+        for i in 1..8_u8 {
+            node.set(IndexPath::new().push(i.into()).push(Direction::RearRightTop), 2);
+        }
+        // 41/64 of the volume is 1, even though more leaves hold 2
+        assert_eq!(*node.dominant_material(), 1);
+        assert_eq!(*node.children[Direction::RearRightTop].as_ref().unwrap().dominant_material(), 2);
+    }
+
+    #[test]
+    fn test_children_present() {
+        let mut node: Node<u16> = Node::new_all(0);
+        assert_eq!(node.children_present().count(), 0);
+
+        node.set(IndexPath::new().push(Direction::FrontLeftBottom).push(Direction::RearLeftTop), 1);
+        node.set(IndexPath::new().push(Direction::FrontLeftBottom).push(Direction::FrontRightBottom), 2);
+        node.set(IndexPath::new().push(Direction::RearRightTop), 3);
+        let present: Vec<Direction> = node.children_present().map(|(dir, _)| dir).collect();
+        assert_eq!(present, vec![Direction::FrontRightBottom, Direction::RearLeftTop]);
+
+        let (_, child) = node.children_present().next().unwrap();
+        assert_eq!(child.data[Direction::FrontLeftBottom], 2);
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let mut node: Node<u16> = Node::new_all(0);
+        let deep = IndexPath::new().push(Direction::FrontLeftBottom).push(Direction::RearRightTop).push(Direction::FrontRightTop);
+        node.set_unmerged(deep, 0);
+        assert_eq!(node.depth(), 3);
+        node.canonicalize();
+        assert_eq!(node.depth(), 1);
+
+        // Only the uniform part of a subtree collapses
+        node.set_unmerged(deep, 1);
+        node.set_unmerged(IndexPath::new().push(Direction::RearLeftTop).push(Direction::RearRightTop), 0);
+        node.canonicalize();
+        assert_eq!(node.depth(), 3);
+        assert!(node.children[Direction::RearRightTop].is_none());
+        assert_eq!(*node.get(deep), 1);
+    }
+
+    #[test]
+    fn test_eq_ignores_subdivision() {
+        let deep = IndexPath::new().put(Direction::FrontLeftBottom).put(Direction::RearRightTop).put(Direction::FrontRightTop);
+        let mut merged: Node<u16> = Node::new_all(0);
+        merged.set(IndexPath::new().put(Direction::RearLeftTop).put(Direction::FrontRightTop), 1);
+        let mut subdivided = merged.clone();
+        subdivided.set_unmerged(deep, 0);
+        assert_eq!(subdivided.depth(), 3);
+        assert!(merged == subdivided);
+        assert!(subdivided == merged);
+
+        subdivided.set_unmerged(deep, 2);
+        assert!(merged != subdivided);
+        assert!(subdivided != merged);
+        subdivided.set_unmerged(deep, 0);
+        subdivided.canonicalize();
+        assert!(merged == subdivided);
+    }
+
+    #[test]
+    fn test_drop() {
+        use std::rc::Rc;
+        use std::cell::Cell;
+
+        struct Counted(u16, Rc<Cell<i64>>);
+        impl Counted {
+            fn new(value: u16, live: &Rc<Cell<i64>>) -> Self {
+                live.set(live.get() + 1);
+                Counted(value, live.clone())
+            }
+        }
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                Counted::new(self.0, &self.1)
+            }
+        }
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.1.set(self.1.get() - 1);
+            }
+        }
+        impl PartialEq for Counted {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        let live = Rc::new(Cell::new(0));
+        let mut node = Node::new_all(Counted::new(0, &live));
+        assert_eq!(live.get(), 8);
+
+        let index_path = IndexPath::new().push(Direction::RearRightTop).push(Direction::FrontLeftBottom);
+        node.set(index_path, Counted::new(1, &live));
+        assert_eq!(live.get(), 16);
+        for i in 0..8_u8 {
+            node.set(IndexPath::new().push(i.into()).push(Direction::FrontLeftBottom), Counted::new(2, &live));
+        }
+        // The subdivided node merged back into the root
+        assert!(node.children.iter().all(|c| c.is_none()));
+        assert_eq!(live.get(), 8);
+
+        drop(node);
+        assert_eq!(live.get(), 0);
+    }
 }
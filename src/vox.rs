@@ -0,0 +1,215 @@
+//! Import and export of MagicaVoxel `.vox` models.
+use std::io::{self, Read, Write};
+use crate::bytes::{FromBytes, ToBytes};
+use crate::chunk::Chunk;
+use crate::grid::Grid;
+
+#[derive(Debug)]
+pub enum VoxError {
+    Io(io::Error),
+    /// The file doesn't start with the `VOX ` magic followed by a `MAIN` chunk.
+    InvalidHeader,
+    /// The file has no `SIZE` and `XYZI` chunks describing a model.
+    MissingModel,
+    /// The model is empty or larger than 256 voxels on a side.
+    InvalidSize((u32, u32, u32)),
+    /// A voxel lies outside of the size declared by the model.
+    OutOfBounds((u8, u8, u8)),
+}
+
+impl From<io::Error> for VoxError {
+    fn from(err: io::Error) -> Self {
+        VoxError::Io(err)
+    }
+}
+
+const MAX_SIZE: u32 = 256;
+const MAX_DEPTH: u8 = 8;
+const VERSION: u32 = 150;
+
+fn read_id<R: Read>(reader: &mut R) -> io::Result<[u8; 4]> {
+    let mut id = [0; 4];
+    reader.read_exact(&mut id)?;
+    Ok(id)
+}
+
+fn skip<R: Read>(reader: &mut R, len: u64) -> io::Result<()> {
+    io::copy(&mut reader.take(len), &mut io::sink())?;
+    Ok(())
+}
+
+impl Chunk<u8> {
+    /// Load the first model of a MagicaVoxel file.
+    /// The chunk holds the palette index of each voxel, with 0 being empty,
+    /// on a grid fine enough to fit the largest side of the model.
+    /// The axes map directly, with z pointing up in both.
+    pub fn from_vox(mut reader: impl Read) -> Result<Chunk<u8>, VoxError> {
+        if &read_id(&mut reader)? != b"VOX " {
+            return Err(VoxError::InvalidHeader);
+        }
+        let _version = u32::read_bytes(&mut reader)?;
+        if &read_id(&mut reader)? != b"MAIN" {
+            return Err(VoxError::InvalidHeader);
+        }
+        let main_content = u32::read_bytes(&mut reader)?;
+        let _main_children = u32::read_bytes(&mut reader)?;
+        skip(&mut reader, main_content as u64)?;
+
+        let mut size = None;
+        loop {
+            let id = match read_id(&mut reader) {
+                Ok(id) => id,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Err(VoxError::MissingModel),
+                Err(err) => return Err(err.into()),
+            };
+            let content = u32::read_bytes(&mut reader)?;
+            let children = u32::read_bytes(&mut reader)?;
+            match &id {
+                b"SIZE" if size.is_none() => {
+                    let model_size = (
+                        u32::read_bytes(&mut reader)?,
+                        u32::read_bytes(&mut reader)?,
+                        u32::read_bytes(&mut reader)?,
+                    );
+                    let extent = model_size.0.max(model_size.1).max(model_size.2);
+                    if model_size.0 == 0 || model_size.1 == 0 || model_size.2 == 0 || extent > MAX_SIZE {
+                        return Err(VoxError::InvalidSize(model_size));
+                    }
+                    skip(&mut reader, (content as u64).saturating_sub(12) + children as u64)?;
+                    size = Some(model_size);
+                }
+                b"XYZI" => {
+                    let size = size.ok_or(VoxError::MissingModel)?;
+                    let extent = size.0.max(size.1).max(size.2);
+                    let depth = (extent.next_power_of_two().trailing_zeros() as u8).max(1);
+                    let mut grid = Grid::filled(depth, 0_u8);
+                    let num_voxels = u32::read_bytes(&mut reader)?;
+                    for _ in 0..num_voxels {
+                        let mut voxel = [0_u8; 4];
+                        reader.read_exact(&mut voxel)?;
+                        let (x, y, z) = (voxel[0], voxel[1], voxel[2]);
+                        if x as u32 >= size.0 || y as u32 >= size.1 || z as u32 >= size.2 {
+                            return Err(VoxError::OutOfBounds((x, y, z)));
+                        }
+                        grid[(x as usize, y as usize, z as usize)] = voxel[3];
+                    }
+                    // Only the first model is loaded
+                    return Ok(Chunk::from_grid(&grid));
+                }
+                // The palette and scene graph chunks don't affect the voxel values
+                _ => skip(&mut reader, content as u64 + children as u64)?,
+            }
+        }
+    }
+}
+
+fn write_chunk_header<W: Write>(writer: &mut W, id: &[u8; 4], content: u32, children: u32) -> io::Result<()> {
+    writer.write_all(id)?;
+    content.write_bytes(writer)?;
+    children.write_bytes(writer)
+}
+
+impl<T: Default + Clone, M> Chunk<T, M> {
+    /// Write the chunk as a MagicaVoxel model with `2^depth` voxels on a side.
+    /// The depth is capped to the 256 voxels per side supported by the format.
+    /// `index` maps each value to its palette index, with 0 leaving the voxel empty.
+    /// Palette entries are RGBA colors, with the first entry used by index 1.
+    pub fn to_vox<F>(&self, mut writer: impl Write, depth: u8, palette: &[[u8; 4]], index: F) -> io::Result<()>
+        where F: Fn(&T) -> u8 {
+        let depth = depth.clamp(1, MAX_DEPTH);
+        let grid = Grid::new(self, depth);
+        let voxels: Vec<[u8; 4]> = grid.iter()
+            .filter_map(|((x, y, z), value)| match index(value) {
+                0 => None,
+                color => Some([x as u8, y as u8, z as u8, color]),
+            })
+            .collect();
+
+        let size_content = 12;
+        let xyzi_content = 4 + 4 * voxels.len() as u32;
+        let rgba_content = 4 * 256;
+        let children = 3 * 12 + size_content + xyzi_content + rgba_content;
+
+        writer.write_all(b"VOX ")?;
+        VERSION.write_bytes(&mut writer)?;
+        write_chunk_header(&mut writer, b"MAIN", 0, children)?;
+
+        write_chunk_header(&mut writer, b"SIZE", size_content, 0)?;
+        let size = grid.get_size() as u32;
+        for _ in 0..3 {
+            size.write_bytes(&mut writer)?;
+        }
+
+        write_chunk_header(&mut writer, b"XYZI", xyzi_content, 0)?;
+        (voxels.len() as u32).write_bytes(&mut writer)?;
+        for voxel in &voxels {
+            writer.write_all(voxel)?;
+        }
+
+        write_chunk_header(&mut writer, b"RGBA", rgba_content, 0)?;
+        for i in 0..256 {
+            writer.write_all(palette.get(i).unwrap_or(&[0; 4]))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index_path::IndexPath;
+
+    // A 2x3x4 model with three voxels
+    const MODEL: &[u8] = &[
+        b'V', b'O', b'X', b' ', 150, 0, 0, 0,
+        b'M', b'A', b'I', b'N', 0, 0, 0, 0, 52, 0, 0, 0,
+        b'S', b'I', b'Z', b'E', 12, 0, 0, 0, 0, 0, 0, 0,
+        2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0,
+        b'X', b'Y', b'Z', b'I', 16, 0, 0, 0, 0, 0, 0, 0,
+        3, 0, 0, 0,
+        0, 0, 0, 1,
+        1, 2, 3, 5,
+        1, 0, 2, 7,
+    ];
+
+    #[test]
+    fn test_from_vox() {
+        let chunk = Chunk::from_vox(MODEL).unwrap();
+        assert_eq!(*chunk.get(IndexPath::from_cell((0, 0, 0), 2)), 1);
+        assert_eq!(*chunk.get(IndexPath::from_cell((1, 2, 3), 2)), 5);
+        assert_eq!(*chunk.get(IndexPath::from_cell((1, 0, 2), 2)), 7);
+        assert_eq!(*chunk.get(IndexPath::from_cell((0, 2, 3), 2)), 0);
+        assert_eq!(chunk.iter_leaf().filter(|voxel| *voxel.get_value() != 0).count(), 3);
+
+        // Unknown chunks are skipped
+        let mut palette_first = MODEL[..20].to_vec();
+        palette_first.extend_from_slice(b"RGBA");
+        palette_first.extend_from_slice(&[4, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 255]);
+        palette_first.extend_from_slice(&MODEL[20..]);
+        let chunk = Chunk::from_vox(&palette_first[..]).unwrap();
+        assert_eq!(*chunk.get(IndexPath::from_cell((1, 2, 3), 2)), 5);
+
+        assert!(matches!(Chunk::from_vox(&MODEL[..8]), Err(VoxError::Io(_))));
+        assert!(matches!(Chunk::from_vox(&b"NOPE"[..]), Err(VoxError::InvalidHeader)));
+        assert!(matches!(Chunk::from_vox(&MODEL[..20]), Err(VoxError::MissingModel)));
+    }
+
+    #[test]
+    fn test_vox_round_trip() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::from_cell((0, 0, 0), 3), 300);
+        chunk.set(IndexPath::from_cell((7, 1, 4), 3), 2);
+        chunk.set(IndexPath::from_cell((3, 6, 5), 3), 3);
+
+        let palette = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+        let mut bytes = Vec::new();
+        chunk.to_vox(&mut bytes, 3, &palette, |value| (*value).min(3) as u8).unwrap();
+        assert_eq!(bytes.len(), 8 + 12 + 24 + 12 + 4 + 3 * 4 + 12 + 1024);
+
+        let imported = Chunk::from_vox(&bytes[..]).unwrap();
+        assert_eq!(*imported.get(IndexPath::from_cell((0, 0, 0), 3)), 3);
+        assert_eq!(*imported.get(IndexPath::from_cell((7, 1, 4), 3)), 2);
+        assert_eq!(*imported.get(IndexPath::from_cell((3, 6, 5), 3)), 3);
+        assert_eq!(imported.iter_leaf().filter(|voxel| *voxel.get_value() != 0).count(), 3);
+    }
+}
@@ -14,7 +14,7 @@ impl<'a, T> Voxel<'a, T> {
     pub fn is_root(&self) -> bool {
         self.index_path.is_empty() // Voxel is root if and only if index path is empty
     }
-    pub fn get_value(&self) -> &T{
+    pub fn get_value(&self) -> &'a T {
         if self.is_root() {
             todo!();
         } else {
@@ -37,6 +37,22 @@ impl<'a, T> Voxel<'a, T> {
     pub fn get_index_path(&self) -> IndexPath {
         self.index_path
     }
+    /// Number of subdivisions from the root. The root is at depth 0.
+    pub fn depth(&self) -> u8 {
+        self.index_path.len()
+    }
+    /// Width of the voxel, in chunk units.
+    pub fn size(&self) -> f32 {
+        self.bounds.get_width()
+    }
+    /// Octant of the voxel within its parent, or None for the root.
+    pub fn octant(&self) -> Option<Direction> {
+        if self.is_root() {
+            None
+        } else {
+            Some(self.index_path.get())
+        }
+    }
     pub fn get_child(&self, dir: Direction) -> Voxel<'a, T> {
         if self.is_root() {
             Voxel {
@@ -65,3 +81,31 @@ impl<'a, T: std::fmt::Debug> std::fmt::Debug for Voxel<'a, T> {
         write!(f, "{:?}", self.get_value())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::Chunk;
+    use crate::direction::Direction;
+    use crate::index_path::IndexPath;
+
+    #[test]
+    fn test_depth_and_octant() {
+        let mut chunk: Chunk<u16> = Chunk::new();
+        chunk.set(IndexPath::new().push(Direction::FrontRightTop).push(Direction::RearLeftBottom), 1);
+
+        let root = chunk.get_root();
+        assert_eq!(root.depth(), 0);
+        assert_eq!(root.size(), 1.0);
+        assert_eq!(root.octant(), None);
+
+        let parent = root.get_child(Direction::RearLeftBottom);
+        assert_eq!(parent.depth(), 1);
+        assert_eq!(parent.octant(), Some(Direction::RearLeftBottom));
+
+        let voxel = parent.get_child(Direction::FrontRightTop);
+        assert_eq!(voxel.depth(), 2);
+        assert_eq!(voxel.size(), 0.25);
+        assert_eq!(voxel.octant(), Some(Direction::FrontRightTop));
+        assert_eq!(*voxel.get_value(), 1);
+    }
+}
@@ -1,20 +1,654 @@
 use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::io::{self, Read, Write};
+use std::num::NonZeroU64;
 use crate::chunk::Chunk;
+use crate::aabb::Aabb;
+use crate::bytes::{ToBytes, FromBytes};
+use crate::index_path::IndexPath;
+use crate::direction::Face;
+use crate::voxel::Voxel;
+use crate::mesher::{Mesh, MeshStyle, MesherRegistry};
 use crate::VoxelData;
+use glam as math;
 
-#[derive(Eq, PartialEq, Hash, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub struct ChunkCoordinates(i64, i64, i64);
 
 impl ChunkCoordinates {
     pub fn new() -> Self {
         Self(0, 0, 0)
     }
+    /// Position of the chunk's minimum corner, in chunk units.
+    pub fn origin(&self) -> math::Vec3A {
+        math::Vec3A::new(self.0 as f32, self.1 as f32, self.2 as f32)
+    }
+    /// Z-order key of the chunk. Sorting by this key keeps nearby chunks close together.
+    /// The coordinates are offset to unsigned and truncated to their low 42 bits, so the
+    /// order is only meaningful for coordinates within ±2^41.
+    pub fn morton(&self) -> u128 {
+        const BITS: u32 = 42;
+        let offset = |coord: i64| (coord.wrapping_add(1 << (BITS - 1)) as u64 & ((1 << BITS) - 1)) as u128;
+        let (x, y, z) = (offset(self.0), offset(self.1), offset(self.2));
+        let mut key = 0;
+        for bit in 0..BITS {
+            key |= ((x >> bit) & 1) << (3 * bit);
+            key |= ((y >> bit) & 1) << (3 * bit + 1);
+            key |= ((z >> bit) & 1) << (3 * bit + 2);
+        }
+        key
+    }
+}
+
+impl From<(i64, i64, i64)> for ChunkCoordinates {
+    fn from(coords: (i64, i64, i64)) -> Self {
+        Self(coords.0, coords.1, coords.2)
+    }
 }
-pub struct World<T> {
-    nodes: HashMap<ChunkCoordinates, Chunk<T>>,
+
+/// The chunks are kept in a `HashMap` hashed with `S`. Chunk lookups happen all the time,
+/// so a faster hasher than the default SipHash can be plugged in with `with_hasher`.
+pub struct World<T, S = RandomState> {
+    nodes: HashMap<ChunkCoordinates, Chunk<T>, S>,
+    scale: f32, // Width of a chunk in world space
+}
+impl<T> Default for World<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<T> World<T> {
+    pub fn new() -> Self {
+        Self::with_scale(1.0)
+    }
+    pub fn with_scale(scale: f32) -> Self {
+        Self::with_capacity(scale, 0)
+    }
+    /// Same as `with_scale`, with room for `capacity` chunks before reallocating.
+    pub fn with_capacity(scale: f32, capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(scale, capacity, RandomState::new())
+    }
 }
-impl<T: VoxelData> World<T> {
+impl<T, S: BuildHasher> World<T, S> {
+    pub fn with_hasher(scale: f32, hasher: S) -> Self {
+        Self::with_capacity_and_hasher(scale, 0, hasher)
+    }
+    pub fn with_capacity_and_hasher(scale: f32, capacity: usize, hasher: S) -> Self {
+        World {
+            nodes: HashMap::with_capacity_and_hasher(capacity, hasher),
+            scale,
+        }
+    }
+    pub fn insert_chunk(&mut self, location: ChunkCoordinates, chunk: Chunk<T>) -> Option<Chunk<T>> {
+        self.nodes.insert(location, chunk)
+    }
+    pub fn remove_chunk(&mut self, location: &ChunkCoordinates) -> Option<Chunk<T>> {
+        self.nodes.remove(location)
+    }
+    /// Release the excess capacity left behind by bulk edits and chunk removals,
+    /// in the chunk table and in each chunk. Returns an estimate of the bytes reclaimed.
+    pub fn compact(&mut self) -> usize {
+        let nodes = self.nodes.capacity();
+        let mut reclaimed: usize = self.nodes.values_mut().map(|chunk| chunk.shrink_to_fit()).sum();
+        self.nodes.shrink_to_fit();
+        reclaimed += (nodes - self.nodes.capacity()) * std::mem::size_of::<(ChunkCoordinates, Chunk<T>)>();
+        reclaimed
+    }
+    pub fn get_scale(&self) -> f32 {
+        self.scale
+    }
+    /// The mesh style of the chunk, or the default one if it isn't loaded.
+    pub fn mesh_style(&self, location: &ChunkCoordinates) -> MeshStyle {
+        self.nodes.get(location).map(|chunk| chunk.mesh_style()).unwrap_or_default()
+    }
+    /// Does nothing if the chunk isn't loaded, as the style is kept on the chunk itself.
+    pub fn set_mesh_style(&mut self, location: &ChunkCoordinates, style: MeshStyle) {
+        if let Some(chunk) = self.nodes.get_mut(location) {
+            chunk.set_mesh_style(style);
+        }
+    }
+    /// Build the chunk with the mesher registered for its style.
+    /// Returns None if no mesher was registered for that style.
+    /// The registry is passed in rather than kept in the world, as its meshers borrow the world.
+    pub fn build_mesh(&self, meshers: &MesherRegistry<T>, location: &ChunkCoordinates, lod: u8) -> Option<Mesh> {
+        meshers.build(self.mesh_style(location), location, lod)
+    }
+    /// All loaded chunks, sorted by their Morton key so that neighbors are visited together.
+    pub fn iter_chunks_morton(&self) -> impl Iterator<Item = (&ChunkCoordinates, &Chunk<T>)> {
+        let mut chunks: Vec<_> = self.nodes.iter().collect();
+        chunks.sort_by_key(|(location, _)| location.morton());
+        chunks.into_iter()
+    }
+    pub fn chunk_aabb(&self, location: &ChunkCoordinates) -> Aabb {
+        let min = location.origin() * self.scale;
+        Aabb::new(min, min + math::Vec3A::splat(self.scale))
+    }
+    pub fn point_to_chunk(&self, point: math::Vec3A) -> ChunkCoordinates {
+        let coords = (point / self.scale).floor();
+        ChunkCoordinates(coords.x() as i64, coords.y() as i64, coords.z() as i64)
+    }
+    /// Coordinates of all chunks touched by the sphere, whether loaded or not.
+    pub fn chunks_in_sphere(&self, center: math::Vec3A, radius: f32) -> Vec<ChunkCoordinates> {
+        let min = self.point_to_chunk(center - math::Vec3A::splat(radius));
+        let max = self.point_to_chunk(center + math::Vec3A::splat(radius));
+        let mut chunks = Vec::new();
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    let location = ChunkCoordinates(x, y, z);
+                    if self.chunk_aabb(&location).intersects_sphere(center, radius) {
+                        chunks.push(location);
+                    }
+                }
+            }
+        }
+        chunks
+    }
+    /// Coordinates of all chunks overlapping the box, whether loaded or not.
+    pub fn chunks_in(&self, region: &Aabb) -> Vec<ChunkCoordinates> {
+        let min = self.point_to_chunk(region.min);
+        let max = self.point_to_chunk(region.max);
+        let mut chunks = Vec::new();
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    let location = ChunkCoordinates(x, y, z);
+                    if self.chunk_aabb(&location).intersects(region) {
+                        chunks.push(location);
+                    }
+                }
+            }
+        }
+        chunks
+    }
+}
+impl<T: VoxelData, S: BuildHasher> World<T, S> {
     pub fn get_chunk_ref(&self, location: &ChunkCoordinates) -> Option<&Chunk<T>> {
         self.nodes.get(location)
     }
+    /// Solid leaves overlapping the box, in all loaded chunks.
+    pub fn iter_solid_in<'a>(&'a self, region: &Aabb) -> impl Iterator<Item = (ChunkCoordinates, Voxel<'a, T>)> + 'a {
+        let region = *region;
+        let scale = self.scale;
+        self.chunks_in(&region)
+            .into_iter()
+            .filter_map(move |location| self.nodes.get(&location).map(|chunk| (location, chunk)))
+            .flat_map(move |(location, chunk)| {
+                // The box in the chunk's own units
+                let origin = location.origin();
+                let local = Aabb::new(region.min / scale - origin, region.max / scale - origin);
+                chunk.iter_leaf_in(&local)
+                    .filter(|voxel| !voxel.get_value().is_empty())
+                    .map(move |voxel| (location, voxel))
+            })
+    }
+}
+
+impl<T: VoxelData, S: BuildHasher> World<T, S> {
+    /// First solid leaf hit by the ray from `origin` towards `dir`, no further than `max_dist`,
+    /// all in world space. The chunks along the ray are visited in order, skipping those that
+    /// aren't loaded. Returns the chunk, the leaf and the point where the ray enters the leaf,
+    /// or None when `dir` has no length.
+    pub fn raycast(&self, origin: math::Vec3A, dir: math::Vec3A, max_dist: f32) -> Option<(ChunkCoordinates, Voxel<'_, T>, math::Vec3A)> {
+        if dir.length_squared() == 0.0 {
+            return None;
+        }
+        let dir = dir.normalize();
+        let start = self.point_to_chunk(origin);
+        let mut cell = [start.0, start.1, start.2];
+        let origin_components = [origin.x(), origin.y(), origin.z()];
+        let dir_components = [dir.x(), dir.y(), dir.z()];
+        // Distance along the ray to the next chunk boundary on each axis, and between two boundaries
+        let mut next = [f32::INFINITY; 3];
+        let mut delta = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            let d = dir_components[axis];
+            if d == 0.0 {
+                continue;
+            }
+            let boundary = if d > 0.0 { cell[axis] + 1 } else { cell[axis] } as f32 * self.scale;
+            next[axis] = (boundary - origin_components[axis]) / d;
+            delta[axis] = self.scale / d.abs();
+        }
+        let mut dist = 0.0;
+        while dist <= max_dist {
+            let location = ChunkCoordinates(cell[0], cell[1], cell[2]);
+            if let Some(chunk) = self.nodes.get(&location) {
+                let local_origin = origin / self.scale - location.origin();
+                if let Some((voxel, hit)) = chunk.raycast(local_origin, dir, max_dist / self.scale) {
+                    return Some((location, voxel, (hit + location.origin()) * self.scale));
+                }
+            }
+            let axis = (0..3)
+                .min_by(|a, b| next[*a].total_cmp(&next[*b]))
+                .unwrap();
+            dist = next[axis];
+            next[axis] += delta[axis];
+            cell[axis] += if dir_components[axis] > 0.0 { 1 } else { -1 };
+        }
+        None
+    }
+}
+
+impl<T: Clone, S: BuildHasher> World<T, S> {
+    /// Values of the six face neighbors of a voxel in the chunk at `location`, in `Face::ALL` order.
+    /// Neighbors are looked up at the voxel's depth, crossing into adjacent chunks as needed.
+    /// Neighbors in chunks that aren't loaded are `None`.
+    pub fn leaf_neighbors(&self, location: &ChunkCoordinates, voxel: &Voxel<T>) -> [Option<T>; 6] {
+        let depth = voxel.get_index_path().len();
+        let size = 1_i64 << depth;
+        let cell = voxel.get_bounds().get_position_with_gridsize(size as u64);
+        let neighbor = |face: Face| -> Option<T> {
+            let offset = face.offset();
+            let cell = [
+                cell.0 as i64 + offset.0 as i64,
+                cell.1 as i64 + offset.1 as i64,
+                cell.2 as i64 + offset.2 as i64,
+            ];
+            let chunk_location = ChunkCoordinates(
+                location.0 + cell[0].div_euclid(size),
+                location.1 + cell[1].div_euclid(size),
+                location.2 + cell[2].div_euclid(size),
+            );
+            let index_path = IndexPath::from_cell((
+                cell[0].rem_euclid(size) as u32,
+                cell[1].rem_euclid(size) as u32,
+                cell[2].rem_euclid(size) as u32,
+            ), depth);
+            self.nodes.get(&chunk_location).map(|chunk| chunk.get(index_path).clone())
+        };
+        [
+            neighbor(Face::ALL[0]),
+            neighbor(Face::ALL[1]),
+            neighbor(Face::ALL[2]),
+            neighbor(Face::ALL[3]),
+            neighbor(Face::ALL[4]),
+            neighbor(Face::ALL[5]),
+        ]
+    }
+}
+
+impl<T: PartialEq, S: BuildHasher> World<T, S> {
+    /// Whether two adjacent chunks agree on the cells touching their shared face,
+    /// compared on a grid subdivided `depth` times. Chunks that aren't loaded never agree.
+    pub fn faces_consistent(&self, a: &ChunkCoordinates, b: &ChunkCoordinates, depth: u8) -> bool {
+        let delta = (b.0 - a.0, b.1 - a.1, b.2 - a.2);
+        let face = match delta {
+            (-1, 0, 0) => Face::Left,
+            (1, 0, 0) => Face::Right,
+            (0, -1, 0) => Face::Front,
+            (0, 1, 0) => Face::Rear,
+            (0, 0, -1) => Face::Bottom,
+            (0, 0, 1) => Face::Top,
+            _ => panic!("Chunks {:?} and {:?} aren't adjacent", a, b),
+        };
+        let (chunk_a, chunk_b) = match (self.nodes.get(a), self.nodes.get(b)) {
+            (Some(chunk_a), Some(chunk_b)) => (chunk_a, chunk_b),
+            _ => return false,
+        };
+        let last = (1_u32 << depth) - 1;
+        // Coordinate along the face normal on each side of the boundary
+        let (near, far) = if face as u8 & 1 == 1 { (last, 0) } else { (0, last) };
+        for i in 0..=last {
+            for j in 0..=last {
+                let (cell_a, cell_b) = match face {
+                    Face::Left | Face::Right => ((near, i, j), (far, i, j)),
+                    Face::Front | Face::Rear => ((i, near, j), (i, far, j)),
+                    Face::Bottom | Face::Top => ((i, j, near), (i, j, far)),
+                };
+                if chunk_a.get(IndexPath::from_cell(cell_a, depth)) != chunk_b.get(IndexPath::from_cell(cell_b, depth)) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+impl<T: Clone + Default + PartialEq + ToBytes, S: BuildHasher> World<T, S> {
+    /// Write the world, omitting chunks and leaves that hold the default value.
+    /// Format: scale, chunk count, then for each chunk its coordinates, leaf count
+    /// and the index path and value of each leaf.
+    pub fn save_sparse<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let default = T::default();
+        let chunks: Vec<(&ChunkCoordinates, Vec<(IndexPath, &T)>)> = self.nodes.iter()
+            .map(|(location, chunk)| {
+                let leaves = chunk.iter_leaf()
+                    .filter(|voxel| *voxel.get_value() != default)
+                    .map(|voxel| (voxel.get_index_path(), voxel.get_value()))
+                    .collect::<Vec<_>>();
+                (location, leaves)
+            })
+            .filter(|(_, leaves)| !leaves.is_empty())
+            .collect();
+
+        self.scale.write_bytes(writer)?;
+        (chunks.len() as u64).write_bytes(writer)?;
+        for (location, leaves) in chunks {
+            location.0.write_bytes(writer)?;
+            location.1.write_bytes(writer)?;
+            location.2.write_bytes(writer)?;
+            (leaves.len() as u64).write_bytes(writer)?;
+            for (index_path, value) in leaves {
+                u64::from(index_path).write_bytes(writer)?;
+                value.write_bytes(writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Clone + Default + PartialEq + FromBytes, S: BuildHasher + Default> World<T, S> {
+    /// Read a world written by `save_sparse`. Omitted leaves take the default value.
+    pub fn load_sparse<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut world = Self::with_hasher(f32::read_bytes(reader)?, S::default());
+        let num_chunks = u64::read_bytes(reader)?;
+        for _ in 0..num_chunks {
+            let location = ChunkCoordinates(
+                i64::read_bytes(reader)?,
+                i64::read_bytes(reader)?,
+                i64::read_bytes(reader)?,
+            );
+            let mut chunk = Chunk::new();
+            let num_leaves = u64::read_bytes(reader)?;
+            for _ in 0..num_leaves {
+                let index_path = NonZeroU64::new(u64::read_bytes(reader)?)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid index path"))?;
+                chunk.set(IndexPath::from(index_path), T::read_bytes(reader)?);
+            }
+            world.insert_chunk(location, chunk);
+        }
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::density::DensityVoxel;
+    use crate::direction::Direction;
+
+    #[test]
+    fn test_chunks_in_sphere() {
+        let world: World<u16> = World::with_scale(2.0);
+        let mut chunks = world.chunks_in_sphere(math::Vec3A::new(2.0, 2.0, 1.0), 1.0);
+        chunks.sort_by_key(|c| (c.0, c.1, c.2));
+        assert_eq!(chunks, vec![
+            ChunkCoordinates(0, 0, 0),
+            ChunkCoordinates(0, 1, 0),
+            ChunkCoordinates(1, 0, 0),
+            ChunkCoordinates(1, 1, 0),
+        ]);
+    }
+
+    #[test]
+    fn test_raycast() {
+        let mut world: World<DensityVoxel> = World::with_scale(2.0);
+        world.insert_chunk((0, 0, 0).into(), Chunk::new());
+        let mut chunk = Chunk::new();
+        chunk.set(IndexPath::from_cell((0, 1, 1), 2), DensityVoxel::new(1, 2));
+        world.insert_chunk((1, 0, 0).into(), chunk);
+        // Solid, but not loaded on the way
+        let mut chunk = Chunk::new();
+        chunk.set(IndexPath::new().put(Direction::from_coords(1, 0, 0)), DensityVoxel::new(1, 3));
+        world.insert_chunk((-1, 0, 0).into(), chunk);
+
+        // Through the empty chunk into the solid one
+        let origin = math::Vec3A::new(0.5, 0.7, 0.8);
+        let (location, voxel, hit) = world.raycast(origin, math::Vec3A::new(1.0, 0.0, 0.0), 10.0).unwrap();
+        assert_eq!(location, ChunkCoordinates(1, 0, 0));
+        assert_eq!(*voxel.get_value(), DensityVoxel::new(1, 2));
+        assert!((hit - math::Vec3A::new(2.0, 0.7, 0.8)).length() < 1e-5);
+
+        // Too short to reach it
+        assert!(world.raycast(origin, math::Vec3A::new(1.0, 0.0, 0.0), 1.0).is_none());
+        // Without a direction
+        assert!(world.raycast(origin, math::Vec3A::zero(), 10.0).is_none());
+        // Passing above the solid leaf, then through unloaded chunks
+        assert!(world.raycast(math::Vec3A::new(0.5, 3.0, 0.8), math::Vec3A::new(1.0, 0.0, 0.0), 10.0).is_none());
+        // Backwards, into the chunk at -1
+        let (location, voxel, hit) = world.raycast(math::Vec3A::new(5.0, 0.2, 0.2), math::Vec3A::new(-1.0, 0.0, 0.0), 10.0).unwrap();
+        assert_eq!(location, ChunkCoordinates(-1, 0, 0));
+        assert_eq!(*voxel.get_value(), DensityVoxel::new(1, 3));
+        assert!((hit - math::Vec3A::new(0.0, 0.2, 0.2)).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut world: World<u16> = World::new();
+        for x in 0..256 {
+            world.insert_chunk((x, 0, 0).into(), Chunk::new());
+        }
+        for x in 4..256 {
+            assert!(world.remove_chunk(&(x, 0, 0).into()).is_some());
+        }
+        assert!(world.remove_chunk(&(4, 0, 0).into()).is_none());
+        let capacity = world.nodes.capacity();
+        assert!(world.compact() > 0);
+        assert!(world.nodes.capacity() < capacity);
+        assert_eq!(world.nodes.len(), 4);
+        assert!(world.nodes.contains_key(&(3, 0, 0).into()));
+        // Nothing left to reclaim
+        assert_eq!(world.compact(), 0);
+    }
+
+    #[test]
+    fn test_morton() {
+        assert!(ChunkCoordinates(0, 0, 0).morton() < ChunkCoordinates(1, 0, 0).morton());
+        assert!(ChunkCoordinates(1, 0, 0).morton() < ChunkCoordinates(0, 1, 0).morton());
+        assert!(ChunkCoordinates(0, 1, 0).morton() < ChunkCoordinates(0, 0, 1).morton());
+        assert!(ChunkCoordinates(-1, -1, -1).morton() < ChunkCoordinates(0, 0, 0).morton());
+        assert_eq!(
+            ChunkCoordinates(1, 1, 1).morton() - ChunkCoordinates(0, 0, 0).morton(),
+            0b111
+        );
+
+        let mut world: World<u16> = World::new();
+        for x in -4..4 {
+            for y in -4..4 {
+                for z in -4..4 {
+                    world.insert_chunk((x, y, z).into(), Chunk::new());
+                }
+            }
+        }
+        let chunks: Vec<ChunkCoordinates> = world.iter_chunks_morton().map(|(location, _)| *location).collect();
+        assert_eq!(chunks.len(), 512);
+        assert!(chunks.windows(2).all(|w| w[0].morton() < w[1].morton()));
+        // Every aligned 2x2x2 block of chunks is visited as a run of 8
+        for block in chunks.chunks(8) {
+            let base = block[0];
+            assert_eq!((base.0 % 2, base.1 % 2, base.2 % 2), (0, 0, 0));
+            for location in block {
+                assert!(location.0 - base.0 < 2 && location.1 - base.1 < 2 && location.2 - base.2 < 2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_iter_solid_in() {
+        let mut world: World<DensityVoxel> = World::with_scale(2.0);
+        let solid = DensityVoxel::new(1, 0);
+        for &x in &[0, 1] {
+            let mut chunk: Chunk<DensityVoxel> = Chunk::new();
+            for cell_x in 0..4 {
+                chunk.set(IndexPath::from_cell((cell_x, 0, 0), 2), solid);
+                chunk.set(IndexPath::from_cell((cell_x, 3, 3), 2), solid);
+            }
+            world.insert_chunk((x, 0, 0).into(), chunk);
+        }
+
+        // Straddles both chunks, covering the two middle cells on each side along the first row
+        let region = Aabb::new(math::Vec3A::new(1.0, 0.0, 0.0), math::Vec3A::new(3.0, 0.5, 0.5));
+        assert_eq!(world.chunks_in(&region).len(), 2);
+        let mut voxels: Vec<_> = world.iter_solid_in(&region)
+            .map(|(location, voxel)| (location, voxel.get_bounds().get_position_with_gridsize(4)))
+            .collect();
+        voxels.sort_by_key(|(location, key)| (location.0, *key));
+        assert_eq!(voxels, vec![
+            (ChunkCoordinates(0, 0, 0), (2, 0, 0)),
+            (ChunkCoordinates(0, 0, 0), (3, 0, 0)),
+            (ChunkCoordinates(1, 0, 0), (0, 0, 0)),
+            (ChunkCoordinates(1, 0, 0), (1, 0, 0)),
+        ]);
+
+        // Unloaded chunks are skipped
+        let region = Aabb::new(math::Vec3A::new(3.0, 0.0, 0.0), math::Vec3A::new(5.0, 0.5, 0.5));
+        assert_eq!(world.iter_solid_in(&region).count(), 2);
+    }
+
+    // Multiplicative hashing, much like FxHash
+    #[derive(Default)]
+    struct MulHasher(u64);
+    impl std::hash::Hasher for MulHasher {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+        fn write(&mut self, bytes: &[u8]) {
+            for byte in bytes {
+                self.write_u64(*byte as u64);
+            }
+        }
+        fn write_u64(&mut self, value: u64) {
+            self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+        }
+        fn write_i64(&mut self, value: i64) {
+            self.write_u64(value as u64);
+        }
+    }
+
+    #[test]
+    fn test_custom_hasher() {
+        type FastWorld<T> = World<T, std::hash::BuildHasherDefault<MulHasher>>;
+        let mut world: FastWorld<DensityVoxel> = World::with_capacity_and_hasher(2.0, 64, Default::default());
+        for x in -2..2 {
+            for y in -2..2 {
+                world.insert_chunk((x, y, -x).into(), Chunk::new());
+            }
+        }
+        let mut chunk: Chunk<DensityVoxel> = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 0, 0), 1), DensityVoxel::new(1, 5));
+        world.insert_chunk((-1, -2, 1).into(), chunk);
+
+        let location = world.point_to_chunk(math::Vec3A::new(-1.5, -3.0, 2.5));
+        assert_eq!(location, ChunkCoordinates(-1, -2, 1));
+        assert!(world.get_chunk_ref(&location).is_some());
+        assert!(world.get_chunk_ref(&(-1, -2, 2).into()).is_none());
+        assert_eq!(*world.get_chunk_ref(&location).unwrap().get(IndexPath::from_cell((1, 0, 0), 1)), DensityVoxel::new(1, 5));
+        assert_eq!(world.chunks_in(&world.chunk_aabb(&location)).len(), 1);
+
+        let mut bytes = Vec::new();
+        world.save_sparse(&mut bytes).unwrap();
+        let loaded: FastWorld<DensityVoxel> = World::load_sparse(&mut bytes.as_slice()).unwrap();
+        assert_eq!(*loaded.get_chunk_ref(&location).unwrap().get(IndexPath::from_cell((1, 0, 0), 1)), DensityVoxel::new(1, 5));
+    }
+
+    #[test]
+    fn test_negative_coordinates() {
+        let mut world: World<u16> = World::with_scale(4.0);
+        let aabb = world.chunk_aabb(&(-1, -1, -1).into());
+        assert_eq!(aabb.min, math::Vec3A::splat(-4.0));
+        assert_eq!(aabb.max, math::Vec3A::zero());
+
+        assert_eq!(world.point_to_chunk(math::Vec3A::new(-0.5, -4.0, -4.5)), ChunkCoordinates(-1, -1, -2));
+        assert_eq!(world.point_to_chunk(math::Vec3A::new(0.0, 3.9, 4.0)), ChunkCoordinates(0, 0, 1));
+        let point = math::Vec3A::new(-1.0, -2.0, -3.0);
+        assert!(world.chunk_aabb(&world.point_to_chunk(point)).contains_point(point));
+
+        world.insert_chunk((-1, -1, -1).into(), Chunk::new());
+        world.insert_chunk((1, 1, 1).into(), Chunk::new());
+        assert!(world.nodes.contains_key(&(-1, -1, -1).into()));
+        assert!(!world.nodes.contains_key(&(-1, 1, 1).into()));
+    }
+
+    #[test]
+    fn test_save_sparse() {
+        fn save(world: &World<u16>) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            world.save_sparse(&mut bytes).unwrap();
+            bytes
+        }
+
+        let mut world: World<u16> = World::with_scale(2.0);
+        for i in 0..64 {
+            world.insert_chunk((i, -i, 0).into(), Chunk::new());
+        }
+        let empty_size = save(&world).len();
+        assert_eq!(empty_size, 4 + 8);
+
+        let mut chunk = Chunk::new();
+        chunk.set(IndexPath::from_cell((1, 2, 3), 3), 7);
+        world.insert_chunk((3, -3, 0).into(), chunk);
+        let one_leaf_size = save(&world).len();
+
+        let mut chunk = Chunk::new();
+        for i in 0..4 {
+            chunk.set(IndexPath::from_cell((i, 2, 3), 3), 9);
+        }
+        world.insert_chunk((5, -5, 0).into(), chunk);
+        let bytes = save(&world);
+        // Each leaf costs an index path and a value on top of the per-chunk header
+        let header_size = 3 * 8 + 8;
+        assert_eq!(one_leaf_size, empty_size + header_size + 10);
+        assert_eq!(bytes.len(), one_leaf_size + header_size + 4 * 10);
+
+        let loaded: World<u16> = World::load_sparse(&mut bytes.as_slice()).unwrap();
+        assert_eq!(loaded.get_scale(), 2.0);
+        assert_eq!(loaded.nodes.len(), 2);
+        let chunk = &loaded.nodes[&(3, -3, 0).into()];
+        assert_eq!(*chunk.get(IndexPath::from_cell((1, 2, 3), 3)), 7);
+        assert_eq!(*chunk.get(IndexPath::from_cell((1, 2, 4), 3)), 0);
+        let chunk = &loaded.nodes[&(5, -5, 0).into()];
+        for i in 0..8 {
+            assert_eq!(*chunk.get(IndexPath::from_cell((i, 2, 3), 3)), if i < 4 { 9 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn test_leaf_neighbors() {
+        let mut world: World<u16> = World::new();
+        let mut chunk = Chunk::new();
+        chunk.set(IndexPath::from_cell((3, 0, 1), 2), 5);
+        chunk.set(IndexPath::from_cell((3, 0, 2), 2), 6);
+        world.insert_chunk((0, 0, 0).into(), chunk);
+        let mut chunk = Chunk::new();
+        chunk.set(IndexPath::from_cell((0, 0, 1), 2), 9);
+        world.insert_chunk((1, 0, 0).into(), chunk);
+
+        let chunk = &world.nodes[&ChunkCoordinates::new()];
+        let index_path = IndexPath::from_cell((3, 0, 1), 2);
+        let voxel = chunk.iter_leaf().find(|voxel| voxel.get_index_path() == index_path).unwrap();
+        assert_eq!(*voxel.get_value(), 5);
+
+        let neighbors = world.leaf_neighbors(&ChunkCoordinates::new(), &voxel);
+        assert_eq!(neighbors[Face::Left as usize], Some(0));
+        assert_eq!(neighbors[Face::Right as usize], Some(9));
+        assert_eq!(neighbors[Face::Front as usize], None);
+        assert_eq!(neighbors[Face::Rear as usize], Some(0));
+        assert_eq!(neighbors[Face::Bottom as usize], Some(0));
+        assert_eq!(neighbors[Face::Top as usize], Some(6));
+    }
+
+    #[test]
+    fn test_faces_consistent() {
+        use crate::world_builder::WorldBuilder;
+
+        let slab = |height: f32| WorldBuilder::from_corner_fn(
+            move |point: math::Vec3A| (point.z() < height) as u16,
+            |a: &u16, b: &u16| a == b,
+            4,
+        );
+        let mut world = World::new();
+        let a: ChunkCoordinates = (0, 0, 0).into();
+        let b: ChunkCoordinates = (1, 0, 0).into();
+        let c: ChunkCoordinates = (0, 1, 0).into();
+        world.insert_chunk(a, slab(0.5).build(&a));
+        world.insert_chunk(b, slab(0.5).build(&b));
+        world.insert_chunk(c, slab(0.25).build(&c));
+
+        assert!(world.faces_consistent(&a, &b, 4));
+        assert!(world.faces_consistent(&b, &a, 4));
+        assert!(!world.faces_consistent(&a, &c, 4));
+        assert!(!world.faces_consistent(&a, &(-1, 0, 0).into(), 4));
+    }
 }
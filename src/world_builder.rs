@@ -2,6 +2,9 @@ use crate::world::ChunkCoordinates;
 use crate::chunk::Chunk;
 use crate::node::Node;
 use crate::bounds::Bounds;
+use crate::direction::Direction;
+use crate::index_path::IndexPath;
+use glam as math;
 
 pub enum Isosurface<T> {
     Uniform(T), // Everything within the bounding box is T
@@ -14,7 +17,7 @@ pub struct WorldBuilder<T, ORACLE: Fn(&ChunkCoordinates, &Bounds) -> Isosurface<
     oracle: ORACLE
 }
 
-impl<T: Copy + Default + PartialEq, ORACLE> WorldBuilder<T, ORACLE>
+impl<T: Clone + Default + PartialEq, ORACLE> WorldBuilder<T, ORACLE>
     where ORACLE: Fn(&ChunkCoordinates, &Bounds) -> Isosurface<T> {
     pub fn new(oracle: ORACLE) -> WorldBuilder<T, ORACLE> {
         WorldBuilder {
@@ -28,6 +31,17 @@ impl<T: Copy + Default + PartialEq, ORACLE> WorldBuilder<T, ORACLE>
         chunk
     }
 
+    /// Same as `build`, but the work is split over calls to `IncrementalBuild::step`
+    /// so that it can be spread across frames.
+    pub fn build_incremental(&self, chunk_coords: &ChunkCoordinates) -> IncrementalBuild<'_, T, ORACLE> {
+        IncrementalBuild {
+            builder: self,
+            chunk_coords: *chunk_coords,
+            chunk: Some(Chunk::new()),
+            stack: vec![(IndexPath::new(), Bounds::new())],
+        }
+    }
+
     fn build_recurse(&self, chunk_coords: &ChunkCoordinates, bounds: &Bounds, node: &mut Node<T>) {
         for (dir, subnode) in node.children.enumerate_mut() {
             let subbounds = bounds.half(dir);
@@ -50,10 +64,81 @@ impl<T: Copy + Default + PartialEq, ORACLE> WorldBuilder<T, ORACLE>
     }
 }
 
+/// A chunk being built by `WorldBuilder::build_incremental`.
+pub struct IncrementalBuild<'a, T, ORACLE: Fn(&ChunkCoordinates, &Bounds) -> Isosurface<T>> {
+    builder: &'a WorldBuilder<T, ORACLE>,
+    chunk_coords: ChunkCoordinates,
+    chunk: Option<Chunk<T>>,
+    // Nodes whose octants are yet to be evaluated
+    stack: Vec<(IndexPath, Bounds)>,
+}
+
+impl<'a, T: Clone + Default + PartialEq, ORACLE> IncrementalBuild<'a, T, ORACLE>
+    where ORACLE: Fn(&ChunkCoordinates, &Bounds) -> Isosurface<T> {
+    /// Evaluate the octants of at most `budget` nodes.
+    /// Returns the chunk once it is complete, and None before and after that.
+    pub fn step(&mut self, budget: usize) -> Option<Chunk<T>> {
+        let chunk = self.chunk.as_mut()?;
+        for _ in 0..budget {
+            let (index_path, bounds) = match self.stack.pop() {
+                Some(work) => work,
+                None => break,
+            };
+            let mut node = &mut chunk.root;
+            for dir in index_path {
+                node = node.children[dir].as_mut().unwrap();
+            }
+            for (dir, subnode) in node.children.enumerate_mut() {
+                let subbounds = bounds.half(dir);
+                match (self.builder.oracle)(&self.chunk_coords, &subbounds) {
+                    Isosurface::Uniform(value) => {
+                        node.data[dir] = value;
+                        *subnode = None;
+                    }
+                    Isosurface::Surface => {
+                        *subnode = Some(Node::new_all(Default::default()));
+                        self.stack.push((index_path.put(dir), subbounds));
+                    }
+                }
+            }
+        }
+        if self.stack.is_empty() {
+            self.chunk.take()
+        } else {
+            None
+        }
+    }
+    pub fn is_done(&self) -> bool {
+        self.stack.is_empty()
+    }
+}
+
+impl<T: Clone + Default + PartialEq + 'static> WorldBuilder<T, WorldBuildIsosurfaceOracle<T>> {
+    /// Builder that samples `f` on the eight corners of each bounds, in chunk units.
+    /// The bounds is uniform when all corners are `equal`, and subdivided otherwise.
+    /// Bounds at `max_depth` are never subdivided and take the value at their center.
+    pub fn from_corner_fn<F, E>(f: F, equal: E, max_depth: u8) -> Self
+        where F: Fn(math::Vec3A) -> T + 'static,
+              E: Fn(&T, &T) -> bool + 'static {
+        Self::new(Box::new(move |chunk_coords: &ChunkCoordinates, bounds: &Bounds| {
+            let origin = chunk_coords.origin();
+            if bounds.depth() >= max_depth {
+                return Isosurface::Uniform(f(origin + bounds.center()));
+            }
+            let corners = Direction::map(|dir| f(origin + bounds.corner(dir)));
+            let first = &corners.data[0];
+            if corners.iter().all(|corner| equal(first, corner)) {
+                Isosurface::Uniform(first.clone())
+            } else {
+                Isosurface::Surface
+            }
+        }))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::mem::size_of;
     use crate::bounds::BoundsSpacialRelationship;
 
     #[test]
@@ -70,5 +155,66 @@ mod tests {
         );
         let chunk = world_builder.build(&ChunkCoordinates::new());
     }
-}
 
+    #[test]
+    fn test_corner_fn() {
+        use crate::index_path::IndexPath;
+
+        let center = math::Vec3A::splat(0.5);
+        let world_builder = WorldBuilder::from_corner_fn(
+            move |point: math::Vec3A| ((point - center).length() < 0.3) as u8,
+            |a: &u8, b: &u8| a == b,
+            4,
+        );
+        let chunk = world_builder.build(&ChunkCoordinates::new());
+
+        assert_eq!(*chunk.get(IndexPath::from_cell((8, 8, 8), 4)), 1);
+        assert_eq!(*chunk.get(IndexPath::from_cell((7, 7, 7), 4)), 1);
+        assert_eq!(*chunk.get(IndexPath::from_cell((0, 0, 0), 4)), 0);
+        assert_eq!(*chunk.get(IndexPath::from_cell((15, 8, 8), 4)), 0);
+
+        let mut boundary = 0;
+        for voxel in chunk.iter_leaf() {
+            let distance = (voxel.get_bounds().center() - center).length();
+            if voxel.get_index_path().len() < 4 {
+                // Coarse leaves are uniform because all of their corners agree
+                let bounds = voxel.get_bounds();
+                for dir in (0..8_u8).map(Direction::from) {
+                    let inside = (bounds.corner(dir) - center).length() < 0.3;
+                    assert_eq!(*voxel.get_value(), inside as u8);
+                }
+            } else {
+                boundary += 1;
+                assert_eq!(*voxel.get_value(), (distance < 0.3) as u8);
+            }
+        }
+        assert!(boundary > 0);
+    }
+
+    #[test]
+    fn test_build_incremental() {
+        let center = math::Vec3A::splat(0.5);
+        let world_builder = WorldBuilder::from_corner_fn(
+            move |point: math::Vec3A| ((point - center).length() < 0.3) as u8,
+            |a: &u8, b: &u8| a == b,
+            4,
+        );
+        let expected = world_builder.build(&ChunkCoordinates::new());
+
+        let mut build = world_builder.build_incremental(&ChunkCoordinates::new());
+        let mut steps = 0;
+        let chunk = loop {
+            steps += 1;
+            if let Some(chunk) = build.step(5) {
+                break chunk;
+            }
+            assert!(!build.is_done());
+        };
+        assert!(steps > 2);
+        assert!(build.is_done());
+        assert!(build.step(5).is_none());
+        assert!(chunk.root == expected.root);
+        assert_eq!(chunk.max_depth(), expected.max_depth());
+        assert_eq!(chunk.iter_leaf().count(), expected.iter_leaf().count());
+    }
+}
@@ -0,0 +1,73 @@
+//! Counts the grid allocations made while meshing. This installs its own global allocator,
+//! so it lives in its own test binary rather than next to `GridPool`.
+use octree::chunk::Chunk;
+use octree::direction::Direction;
+use octree::grid::GridPool;
+use octree::index_path::IndexPath;
+use octree::mesher::{Mesher, MarchingCubesMesher};
+use octree::world::World;
+use octree::VoxelData;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// Counts allocations made on the current thread with the size of the grid under test
+struct CountingAllocator;
+thread_local! {
+    static TRACKED_SIZE: Cell<usize> = const { Cell::new(0) };
+    static TRACKED_COUNT: Cell<usize> = const { Cell::new(0) };
+}
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = TRACKED_SIZE.try_with(|size| {
+            if size.get() == layout.size() {
+                TRACKED_COUNT.with(|count| count.set(count.get() + 1));
+            }
+        });
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+struct Material([u8; 5]); // The grids are then the only allocations of their size
+impl VoxelData for Material {
+    fn is_empty(&self) -> bool {
+        self.0[0] == 0
+    }
+}
+
+fn build_chunk() -> Chunk<Material> {
+    let mut chunk: Chunk<Material> = Chunk::new();
+    chunk.set(IndexPath::new().push(Direction::RearLeftTop), Material([1; 5]));
+    chunk
+}
+
+#[test]
+fn test_pool() {
+    let lod = 3;
+    TRACKED_SIZE.with(|size| size.set(std::mem::size_of::<Material>() << (lod * 3)));
+
+    let chunk = build_chunk();
+    let mut pool = GridPool::new();
+    for _ in 0..16 {
+        let grid = pool.get(&chunk, lod);
+        assert_eq!(grid[(0, 0, 0)], Material::default());
+        pool.recycle(grid);
+    }
+    assert_eq!(TRACKED_COUNT.with(|count| count.get()), 1);
+
+    let mut world = World::new();
+    for i in 0..16 {
+        world.insert_chunk((i, 0, 0).into(), build_chunk());
+    }
+    TRACKED_COUNT.with(|count| count.set(0));
+    let mesher = MarchingCubesMesher::new(&world);
+    for i in 0..16 {
+        mesher.build(&(i, 0, 0).into(), lod);
+    }
+    assert_eq!(TRACKED_COUNT.with(|count| count.get()), 1);
+}